@@ -55,7 +55,7 @@ impl IngredientDefinition {
                     }
                 }
                 Rule::ingredientName => {
-                    name = Some(pair.as_str().to_string());
+                    name = Some(pair.as_str().to_lowercase());
                 }
                 _ => {}
             }
@@ -104,7 +104,7 @@ impl IngredientDefinitionList {
                 }
             }
         }
-        return Ok(IngredientDefinitionList { definitions })
+        Ok(IngredientDefinitionList { definitions })
     }
 }
 
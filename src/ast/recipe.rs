@@ -1,29 +1,35 @@
-use getset::Getters;
+use getset::{CopyGetters, Getters};
 use linked_hash_map::LinkedHashMap;
 use pest::iterators::Pair;
 
 use crate::ast::ingredient::IngredientDefinitionList;
-use crate::ast::statement::Statement;
-use crate::ast::util::{PairsExtensions, ParseResult};
+use crate::ast::statement::SpannedStatement;
+use crate::ast::util::{PairsExtensions, ParseError, ParseResult, Span};
 use crate::grammar::Rule;
 
 pub type Recipes = LinkedHashMap<String, Recipe>;
 
 pub fn recipes_from(recipes_rule: Pair<Rule>) -> ParseResult<Recipes> {
     let mut recipe_rules = recipes_rule.into_inner();
+    while recipe_rules.try_next(Rule::borrowLine).is_some() {}
     let mut recipes = Recipes::new();
-    loop {
-        if let Some(recipe_rule) = recipe_rules.try_next(Rule::recipe) {
-            let recipe = Recipe::from(recipe_rule)?;
-            recipes.insert(recipe.name().clone(), recipe);
-        } else {
-            break;
-        }
+    while let Some(recipe_rule) = recipe_rules.try_next(Rule::recipe) {
+        let recipe = Recipe::from(recipe_rule)?;
+        recipes.insert(recipe.name().clone(), recipe);
     }
     Ok(recipes)
 }
 
-#[derive(Debug, Getters)]
+/// The files named by the `Borrow recipes from` lines in front of the recipes, as written.
+pub fn borrowed_files(recipes_rule: &Pair<Rule>) -> Vec<String> {
+    recipes_rule.clone().into_inner()
+        .filter(|pair| pair.as_rule() == Rule::borrowLine)
+        .filter_map(|pair| pair.into_inner().next())
+        .map(|path| path.as_str().to_string())
+        .collect()
+}
+
+#[derive(Debug, CopyGetters, Getters)]
 pub struct Recipe {
     #[getset(get="pub")]
     name: String,
@@ -31,8 +37,14 @@ pub struct Recipe {
     comment: Option<String>,
     #[getset(get="pub")]
     ingredients: IngredientDefinitionList,
+    /// The declared cooking time in minutes.
     #[getset(get="pub")]
-    statements: Vec<Statement>,
+    cooking_time: Option<f64>,
+    #[getset(get="pub")]
+    statements: Vec<SpannedStatement>,
+    /// Where the recipe is in its source.
+    #[getset(get_copy="pub")]
+    span: Span,
 }
 
 impl Recipe {
@@ -41,7 +53,9 @@ impl Recipe {
             name: name.to_string(),
             comment: Some(comment.to_string()),
             ingredients: IngredientDefinitionList::empty(),
+            cooking_time: None,
             statements: Vec::new(),
+            span: Span { begin: (1, 1), end: (1, 1) },
         }
     }
 
@@ -51,17 +65,35 @@ impl Recipe {
         let name = recipe_pairs.expect_next(Rule::recipeName, &recipe_span)?.as_str().to_lowercase();
         let comment = recipe_pairs.try_next(Rule::recipeComment).map(|val| val.as_str().to_string());
         let ingredient_list = recipe_pairs.expect_next(Rule::ingredientList, &recipe_span).and_then(IngredientDefinitionList::from)?;
+        let cooking_time = recipe_pairs.try_next(Rule::cookingTime).map(cooking_time_from).transpose()?;
+        // The oven temperature is just part of the recipe's prose.
+        recipe_pairs.try_next(Rule::ovenTemperature);
         let method_pair = recipe_pairs.expect_next(Rule::method, &recipe_span)?;
         let mut statements = Vec::new();
         for pair in method_pair.into_inner() {
-            statements.push(Statement::from(pair)?);
+            statements.push(SpannedStatement::from(pair)?);
         }
 
         Ok(Recipe {
             name,
             comment,
             ingredients: ingredient_list,
+            cooking_time,
             statements,
+            span: Span::from(&recipe_span),
         })
     }
 }
+
+/// Reads a cooking time in minutes.
+fn cooking_time_from(pair: Pair<Rule>) -> ParseResult<f64> {
+    let span = pair.as_span();
+    let mut pairs = pair.into_inner();
+    let amount = pairs.expect_next(Rule::floatNumber, &span)?;
+    let amount: f64 = amount.as_str().parse().map_err(|err| ParseError::Generic {
+        message: format!("failed to parse cooking time: {}", err),
+        begin: amount.as_span().start_pos().line_col(),
+    })?;
+    let unit = pairs.expect_next(Rule::cookingTimeUnit, &span)?;
+    Ok(if unit.as_str().starts_with("hour") { amount * 60.0 } else { amount })
+}
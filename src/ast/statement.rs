@@ -1,16 +1,19 @@
+use getset::{CopyGetters, Getters};
 use pest::iterators::Pair;
 
 use crate::ast::ingredient::expect_ingredient_name;
-use crate::ast::util::{BakingDishId, expect_baking_dish, expect_mixing_bowl, mixin_bowl_from, MixingBowlId, PairsExtensions, ParseError, ParseResult};
+use crate::ast::util::{baking_dish_from, BakingDishId, expect_baking_dish, expect_mixing_bowl, expect_refrigerator, mixin_bowl_from, MixingBowlId, PairsExtensions, ParseError, ParseResult, refrigerator_from, RefrigeratorId, Span};
 use crate::grammar::Rule;
 
 #[derive(Debug)]
 pub enum Statement {
     Read {
-        ingredient: String
+        ingredient: String,
+        refrigerator: RefrigeratorId,
     },
     CheckInput {
-        ingredient: String
+        ingredient: String,
+        refrigerator: RefrigeratorId,
     },
     Push {
         ingredient: String,
@@ -72,10 +75,28 @@ pub enum Statement {
     ExamineBowl {
         mixing_bowl: MixingBowlId,
     },
+    ExamineDish {
+        baking_dish: BakingDishId,
+    },
+    ExamineRefrigerator {
+        refrigerator: RefrigeratorId,
+    },
+    ExamineKitchen,
+    /// Fails unless the ingredient is set.
+    Taste {
+        ingredient: String,
+    },
+    /// Fails unless the top of the mixing bowl has the value of the ingredient.
+    TasteBowl {
+        mixing_bowl: MixingBowlId,
+        ingredient: String,
+    },
     Loop {
+        /// The verb of the loop, in lowercase.
+        verb: String,
         test_ingredient: String,
         decrement_ingredient: Option<String>,
-        statements: Vec<Statement>,
+        statements: Vec<SpannedStatement>,
     },
     BreakLoop,
     CallAuxiliary {
@@ -84,6 +105,29 @@ pub enum Statement {
     Return {
         count: MixingBowlId,
     },
+    /// Ends cooking altogether, with the value of the ingredient as the exit code.
+    Exit {
+        ingredient: String,
+    },
+}
+
+/// A statement together with where it was found in the method.
+#[derive(Debug, CopyGetters, Getters)]
+pub struct SpannedStatement {
+    #[getset(get="pub")]
+    statement: Statement,
+    #[getset(get_copy="pub")]
+    span: Span,
+}
+
+impl SpannedStatement {
+    pub fn from(pair: Pair<Rule>) -> ParseResult<SpannedStatement> {
+        let span = Span::from(&pair.as_span());
+        Ok(SpannedStatement {
+            statement: Statement::from(pair)?,
+            span,
+        })
+    }
 }
 
 impl Statement {
@@ -95,11 +139,14 @@ impl Statement {
             Rule::takeStatement => {
                 Ok(Statement::Read {
                     ingredient: expect_ingredient_name(&mut pairs, &span)?,
+                    refrigerator: expect_refrigerator(&mut pairs)?,
                 })
             }
             Rule::checkStatement => {
+                let refrigerator = expect_refrigerator(&mut pairs)?;
                 Ok(Statement::CheckInput {
                     ingredient: expect_ingredient_name(&mut pairs, &span)?,
+                    refrigerator,
                 })
             }
             Rule::putStatement => {
@@ -201,12 +248,9 @@ impl Statement {
             Rule::pourBowlStatement => {
                 let mut mixing_bowl = 0;
                 let next = pairs.peek().unwrap();
-                match next.as_rule() {
-                    Rule::mixingBowl => {
-                        pairs.next();
-                        mixing_bowl = mixin_bowl_from(next)?
-                    }
-                    _ => {}
+                if next.as_rule() == Rule::mixingBowl {
+                    pairs.next();
+                    mixing_bowl = mixin_bowl_from(next)?
                 }
                 Ok(Statement::SetResult {
                     mixing_bowl,
@@ -218,12 +262,34 @@ impl Statement {
                     Ok(Statement::Examine {
                         ingredient: pair.as_str().to_lowercase(),
                     })
+                } else if let Some(pair) = pairs.try_next(Rule::bakingDish) {
+                    Ok(Statement::ExamineDish {
+                        baking_dish: baking_dish_from(pair)?,
+                    })
+                } else if let Some(pair) = pairs.try_next(Rule::refrigerator) {
+                    Ok(Statement::ExamineRefrigerator {
+                        refrigerator: refrigerator_from(pair)?,
+                    })
+                } else if pairs.try_next(Rule::kitchen).is_some() {
+                    Ok(Statement::ExamineKitchen)
                 } else {
                     Ok(Statement::ExamineBowl {
                         mixing_bowl: expect_mixing_bowl(&mut pairs)?,
                     })
                 }
             }
+            Rule::tasteStatement => {
+                if let Some(pair) = pairs.try_next(Rule::mixingBowl) {
+                    Ok(Statement::TasteBowl {
+                        mixing_bowl: mixin_bowl_from(pair)?,
+                        ingredient: expect_ingredient_name(&mut pairs, &span)?,
+                    })
+                } else {
+                    Ok(Statement::Taste {
+                        ingredient: expect_ingredient_name(&mut pairs, &span)?,
+                    })
+                }
+            }
             Rule::loopBlock => {
                 let begin = pairs.expect_next(Rule::loopBeginStatement, &span)?;
                 let begin_span = begin.as_span();
@@ -235,7 +301,7 @@ impl Statement {
                 let mut statements = Vec::new();
                 for pair in pairs {
                     if pair.as_rule() != Rule::loopEndStatement {
-                        statements.push(Statement::from(pair)?);
+                        statements.push(SpannedStatement::from(pair)?);
                     } else {
                         let end_span = pair.as_span();
                         let mut end = pair.into_inner();
@@ -254,6 +320,7 @@ impl Statement {
                 }
 
                 Ok(Statement::Loop {
+                    verb,
                     statements,
                     test_ingredient,
                     decrement_ingredient,
@@ -275,6 +342,11 @@ impl Statement {
                         })?
                 })
             }
+            Rule::throwAwayStatement => {
+                Ok(Statement::Exit {
+                    ingredient: expect_ingredient_name(&mut pairs, &span)?,
+                })
+            }
             Rule::servesStatement => {
                 Ok(Statement::Return {
                     count: pairs.expect_next(Rule::servesPeople, &span)?.as_str().parse()
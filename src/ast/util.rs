@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+
 use pest::iterators::{Pair, Pairs};
 use pest::{RuleType};
 use crate::grammar::Rule;
@@ -5,6 +7,29 @@ use crate::grammar::Rule;
 pub type ParseResult<T> = Result<T, ParseError>;
 pub type MixingBowlId = u8;
 pub type BakingDishId = u8;
+pub type RefrigeratorId = u8;
+
+/// Where something is in the source, as `(line, column)` pairs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Span {
+    pub begin: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl Span {
+    pub fn from(span: &pest::Span) -> Span {
+        Span {
+            begin: span.start_pos().line_col(),
+            end: span.end_pos().line_col(),
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.begin.0, self.begin.1)
+    }
+}
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ParseError {
@@ -19,6 +44,15 @@ pub enum ParseError {
     }
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Generic { message, begin } => write!(f, "{}:{}: {}", begin.0, begin.1, message),
+            ParseError::RuleNotFound { message, begin, .. } => write!(f, "{}:{}: {}", begin.0, begin.1, message),
+        }
+    }
+}
+
 pub fn mixin_bowl_from(pair: Pair<Rule>) -> ParseResult<MixingBowlId> {
     pair.into_inner().try_next(Rule::mixingBowlNumber)
         .map(|pair| {
@@ -45,23 +79,41 @@ pub fn baking_dish_from(pair: Pair<Rule>) -> ParseResult<BakingDishId> {
         .unwrap_or(Ok(1))
 }
 
+pub fn refrigerator_from(pair: Pair<Rule>) -> ParseResult<RefrigeratorId> {
+    pair.into_inner().try_next(Rule::refrigeratorNumber)
+        .map(|pair| {
+            pair.as_str().parse().map_err(|err| {
+                ParseError::Generic {
+                    message: format!("failed to parse refrigerator number: {}", err),
+                    begin: pair.as_span().start_pos().line_col(),
+                }
+            })
+        })
+        .unwrap_or(Ok(1))
+}
+
 pub fn expect_mixing_bowl(pairs: &mut Pairs<Rule>) -> ParseResult<MixingBowlId> {
     pairs.try_next(Rule::mixingBowl)
-        .map_or(Ok(1), |val| mixin_bowl_from(val))
+        .map_or(Ok(1), mixin_bowl_from)
 }
 
-pub fn expect_baking_dish(pairs: &mut Pairs<Rule>) -> ParseResult<MixingBowlId> {
+pub fn expect_baking_dish(pairs: &mut Pairs<Rule>) -> ParseResult<BakingDishId> {
     pairs.try_next(Rule::bakingDish)
-        .map_or(Ok(1), |val| mixin_bowl_from(val))
+        .map_or(Ok(1), baking_dish_from)
+}
+
+pub fn expect_refrigerator(pairs: &mut Pairs<Rule>) -> ParseResult<RefrigeratorId> {
+    pairs.try_next(Rule::refrigerator)
+        .map_or(Ok(1), refrigerator_from)
 }
 
 pub trait PairsExtensions<R> {
-    fn expect_next(&mut self, rule: R, parent_span: &pest::Span) -> ParseResult<Pair<R>>;
-    fn try_next(&mut self, rule: R) -> Option<Pair<R>>;
+    fn expect_next(&mut self, rule: R, parent_span: &pest::Span) -> ParseResult<Pair<'_, R>>;
+    fn try_next(&mut self, rule: R) -> Option<Pair<'_, R>>;
 }
 
 impl<R: RuleType> PairsExtensions<R> for Pairs<'_, R> {
-    fn expect_next(&mut self, rule: R, parent_span: &pest::Span) -> ParseResult<Pair<R>> {
+    fn expect_next(&mut self, rule: R, parent_span: &pest::Span) -> ParseResult<Pair<'_, R>> {
         if let Some(next) = self.next() {
             if next.as_rule() == rule {
                 Ok(next)
@@ -81,7 +133,7 @@ impl<R: RuleType> PairsExtensions<R> for Pairs<'_, R> {
         }
     }
 
-    fn try_next(&mut self, rule: R) -> Option<Pair<R>> {
+    fn try_next(&mut self, rule: R) -> Option<Pair<'_, R>> {
         self.peek().and_then(|pair| {
             if pair.as_rule() == rule {
                 self.next();
@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+use crate::cli::{load_exit_code, load_recipes};
+
+pub fn print_ast(recipe: &str, include: &[PathBuf]) -> i32 {
+    match load_recipes(recipe, include) {
+        Ok(recipes) => {
+            for recipe in recipes.values() {
+                println!("{:#?}", recipe);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            load_exit_code(&err)
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use chef_rs::ast::statement::{SpannedStatement, Statement};
+use chef_rs::ast::util::Span;
+use chef_rs::cookbook;
+
+use crate::cli::{load_exit_code, load_manifest, load_recipes, project_files, search_path, FAILURE_EXIT_CODE, IO_EXIT_CODE, STDIN_NAME};
+
+/// The recipes that the given statements serve with, with the location of each `Serve with`.
+fn served_recipes(statements: &[SpannedStatement], served: &mut Vec<(String, Span)>) {
+    for statement in statements {
+        match statement.statement() {
+            Statement::CallAuxiliary { recipe } => served.push((recipe.clone(), statement.span())),
+            Statement::Loop { statements, .. } => served_recipes(statements, served),
+            _ => {}
+        }
+    }
+}
+
+/// Loads the given files and reports every problem, checking that the first one has the entry recipe.
+/// Returns the exit code of the first problem, or 0 if everything is fine.
+fn check_files(files: &[String], include: &[PathBuf], entry: Option<&str>) -> i32 {
+    let mut problems = 0;
+    let mut exit_code = 0;
+    for (i, file) in files.iter().enumerate() {
+        let name = if file == "-" { STDIN_NAME } else { file.as_str() };
+        let recipes = match load_recipes(file, include) {
+            Ok(recipes) => recipes,
+            Err(err) => {
+                eprintln!("{}", err);
+                problems += 1;
+                if exit_code == 0 {
+                    exit_code = load_exit_code(&err);
+                }
+                continue;
+            }
+        };
+        if i == 0 {
+            if let Some(entry) = entry {
+                if !recipes.contains_key(&entry.trim().to_lowercase()) {
+                    eprintln!("{}: there is no entry recipe {}", name, entry);
+                    problems += 1;
+                }
+            }
+        }
+        for (recipe_name, recipe) in &recipes {
+            let mut served = Vec::new();
+            served_recipes(recipe.statements(), &mut served);
+            for (served, span) in served {
+                if !recipes.contains_key(&served) && cookbook::find(&served).is_none() {
+                    eprintln!("{}:{} in {}: serves with unknown recipe {}", name, span, recipe_name, served);
+                    problems += 1;
+                }
+            }
+        }
+    }
+    if problems == 0 {
+        println!("checked {} recipe files, everything is fine", files.len());
+    } else {
+        eprintln!("checked {} recipe files, found {} {}", files.len(), problems, if problems == 1 { "problem" } else { "problems" });
+    }
+    match (problems, exit_code) {
+        (0, _) => 0,
+        (_, 0) => FAILURE_EXIT_CODE,
+        (_, exit_code) => exit_code,
+    }
+}
+
+pub fn check(recipe: Option<String>, manifest: Option<PathBuf>, include: Vec<PathBuf>) -> i32 {
+    if let Some(recipe) = recipe {
+        return check_files(&[recipe], &include, None);
+    }
+    let manifest = match load_manifest(manifest) {
+        Ok(manifest) => manifest,
+        Err(code) => return code,
+    };
+    match project_files(&manifest) {
+        Ok(files) => {
+            let files: Vec<_> = files.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+            check_files(&files, &search_path(&manifest, include), manifest.entry.as_deref())
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            IO_EXIT_CODE
+        }
+    }
+}
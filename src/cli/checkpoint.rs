@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use chef_rs::interpreter::execution::Execution;
+use chef_rs::interpreter::interpreter::{Interpreter, InterpreterResult};
+use chef_rs::interpreter::output::Output;
+use chef_rs::interpreter::refrigerator::Refrigerators;
+use chef_rs::interpreter::snapshot::Snapshot;
+
+/// Resumes cooking from the given snapshot, or starts cooking the entry recipe or else the main recipe.
+pub fn start<'a>(
+    resume: Option<&Path>,
+    entry: Option<&str>,
+    interpreter: &'a Interpreter,
+    refrigerators: &'a mut Refrigerators,
+    output: &'a mut Output,
+) -> InterpreterResult<Execution<'a>> {
+    match resume {
+        Some(path) => interpreter.resume(&Snapshot::load(path)?, refrigerators, output),
+        None => match entry {
+            Some(entry) => interpreter.start(entry, refrigerators, output),
+            None => interpreter.start(interpreter.main_recipe()?, refrigerators, output),
+        },
+    }
+}
+
+/// Where and how often snapshots of the kitchen are saved while cooking.
+pub struct Checkpoint<'a> {
+    path: Option<&'a Path>,
+    every: Option<u64>,
+    steps: u64,
+}
+
+impl<'a> Checkpoint<'a> {
+    /// Without a path, no snapshots are saved at all.
+    pub fn new(path: Option<&'a Path>, every: Option<u64>) -> Checkpoint<'a> {
+        Checkpoint { path, every, steps: 0 }
+    }
+
+    /// Counts a statement that has been run and saves a snapshot if one is due.
+    pub fn step(&mut self, execution: &Execution) -> Result<(), String> {
+        self.steps += 1;
+        if let (Some(path), Some(every)) = (self.path, self.every) {
+            if self.steps.is_multiple_of(every.max(1)) {
+                execution.snapshot().save(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves a snapshot because cooking has stopped early.
+    pub fn save(&self, execution: &Execution) -> Result<(), String> {
+        match self.path {
+            Some(path) => execution.snapshot().save(path),
+            None => Ok(()),
+        }
+    }
+}
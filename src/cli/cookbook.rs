@@ -0,0 +1,43 @@
+use clap::Subcommand;
+
+use chef_rs::cookbook::{self, COOKBOOK_VERSION};
+
+use crate::cli::FAILURE_EXIT_CODE;
+
+#[derive(Subcommand)]
+pub enum CookbookCommand {
+    /// List the recipes of the cookbook
+    List,
+    /// Print the source of a recipe of the cookbook
+    Show {
+        recipe: String,
+    },
+}
+
+pub fn browse_cookbook(command: &CookbookCommand) -> i32 {
+    match command {
+        CookbookCommand::List => {
+            println!("cookbook version {}", COOKBOOK_VERSION);
+            let recipes = cookbook::recipes();
+            for recipe in cookbook::RECIPES {
+                let comment = recipes.get(&recipe.name.to_lowercase())
+                    .and_then(|parsed| parsed.comment().as_ref())
+                    .map(|comment| comment.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .unwrap_or_default();
+                let summary = comment.split_inclusive(". ").next().unwrap_or_default().trim_end();
+                println!("  {}: {}", recipe.name, summary);
+            }
+            0
+        }
+        CookbookCommand::Show { recipe } => match cookbook::find(recipe) {
+            Some(recipe) => {
+                print!("{}", recipe.source);
+                0
+            }
+            None => {
+                eprintln!("the cookbook has no recipe {}", recipe);
+                FAILURE_EXIT_CODE
+            }
+        },
+    }
+}
@@ -0,0 +1,14 @@
+use std::path::Path;
+
+use chef_rs::coverage::CoverageReport;
+
+/// Writes a coverage report as an lcov tracefile. Returns whether that worked.
+pub fn write_coverage(path: &Path, report: &CoverageReport) -> bool {
+    match std::fs::write(path, report.lcov()) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("failed to write coverage report {}: {}", path.display(), err);
+            false
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use chef_rs::grammar;
+use chef_rs::loader::LoadError;
+
+use crate::cli::{load_exit_code, read_source, FAILURE_EXIT_CODE, IO_EXIT_CODE, STDIN_NAME};
+
+/// Formats the given files and prints them, writes them back or reports those that aren't formatted.
+pub fn format_files(recipes: &[String], write: bool, check: bool) -> i32 {
+    let mut unformatted = 0;
+    for recipe in recipes {
+        let name = if recipe == "-" { STDIN_NAME } else { recipe.as_str() };
+        let result = read_source(recipe).and_then(|code| grammar::format(&code)
+            .map(|formatted| (code, formatted))
+            .map_err(|error| LoadError::Parse { path: PathBuf::from(name), error }));
+        let (code, formatted) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("{}", err);
+                return load_exit_code(&err);
+            }
+        };
+        if check {
+            if code != formatted {
+                println!("{}", name);
+                unformatted += 1;
+            }
+        } else if write && recipe != "-" {
+            if code != formatted {
+                if let Err(err) = std::fs::write(recipe, formatted) {
+                    eprintln!("failed to write {}: {}", name, err);
+                    return IO_EXIT_CODE;
+                }
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+    if unformatted == 0 { 0 } else { FAILURE_EXIT_CODE }
+}
@@ -0,0 +1,133 @@
+//! The subcommands of the `chef` binary, one module each, and what they share.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chef_rs::ast::recipe::Recipes;
+use chef_rs::loader::{LoadError, Loader, RecipeLocation};
+use chef_rs::manifest::{Manifest, MANIFEST_NAME};
+
+pub mod ast;
+pub mod check;
+pub mod checkpoint;
+pub mod cookbook;
+pub mod coverage;
+pub mod fmt;
+pub mod run;
+pub mod test;
+
+/// The exit codes as the help describes them.
+pub const EXIT_STATUS_HELP: &str = "EXIT STATUS:
+    0      everything went fine
+    1      cooking failed, or check, test or fmt --check found problems
+    2      the command line is invalid, or there is no Cookbook.toml to work on
+    3      a file couldn't be read or written, or the manifest couldn't be understood
+    4      a recipe file isn't valid Chef
+    5      the recipes don't make sense together
+    130    cooking has been interrupted with Ctrl-C
+
+A recipe that is thrown away exits with the value of the ingredient instead, which has to be from 0 to 255.
+Its status is passed on as it is, so 1 to 5 and 130 can't be told apart from the ones above.";
+
+/// The exit code when cooking fails or `check` or `test` find problems.
+pub const FAILURE_EXIT_CODE: i32 = 1;
+/// The exit code when the command line is invalid, like clap reports it, or there is no manifest to work on.
+pub const USAGE_EXIT_CODE: i32 = 2;
+/// The exit code when a file can't be read or written, or the manifest can't be understood.
+pub const IO_EXIT_CODE: i32 = 3;
+/// The exit code when a recipe file isn't valid Chef.
+pub const PARSE_EXIT_CODE: i32 = 4;
+/// The exit code when recipe files are valid Chef, but their recipes don't make sense together.
+pub const TRANSFORM_EXIT_CODE: i32 = 5;
+/// The exit code after Ctrl-C, like shells report processes killed by SIGINT.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+pub fn load_exit_code(err: &LoadError) -> i32 {
+    match err {
+        LoadError::Io { .. } | LoadError::NotFound { .. } => IO_EXIT_CODE,
+        LoadError::Parse { .. } => PARSE_EXIT_CODE,
+        LoadError::Transform { .. } | LoadError::Cycle { .. } | LoadError::Conflict { .. } => TRANSFORM_EXIT_CODE,
+    }
+}
+
+/// What recipes read from stdin are called in errors. Files they borrow are looked up relative to the working directory.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Reads a recipe file, or stdin if the file is `-`.
+pub fn read_source(recipe: &str) -> Result<String, LoadError> {
+    if recipe == "-" {
+        let mut code = String::new();
+        std::io::stdin().read_to_string(&mut code)
+            .map_err(|error| LoadError::Io { path: PathBuf::from(STDIN_NAME), error })?;
+        Ok(code)
+    } else {
+        std::fs::read_to_string(recipe).map_err(|error| LoadError::Io { path: PathBuf::from(recipe), error })
+    }
+}
+
+/// Loads a recipe file, or stdin if the file is `-`, together with all files it borrows from.
+pub fn load_recipes(recipe: &str, include: &[PathBuf]) -> Result<Recipes, LoadError> {
+    load_located_recipes(recipe, include).map(|(recipes, _)| recipes)
+}
+
+/// Like [`load_recipes`], together with where each recipe has been defined.
+pub fn load_located_recipes(recipe: &str, include: &[PathBuf]) -> Result<(Recipes, HashMap<String, RecipeLocation>), LoadError> {
+    let loader = Loader::new(include.to_vec());
+    if recipe == "-" {
+        loader.load_source_located(Path::new(STDIN_NAME), &read_source(recipe)?)
+    } else {
+        loader.load_located(Path::new(recipe))
+    }
+}
+
+/// Loads the given manifest or the one closest to the working directory.
+/// Reports why the manifest couldn't be loaded and fails with the exit code for that:
+/// having no manifest to work on is a usage error, a manifest that can't be read or understood an I/O error.
+pub fn load_manifest(path: Option<PathBuf>) -> Result<Manifest, i32> {
+    let path = match path.or_else(|| std::env::current_dir().ok().and_then(|dir| Manifest::find(&dir))) {
+        Some(path) => path,
+        None => {
+            eprintln!("could not find {} in this directory or any of its parents, give a recipe file or --manifest", MANIFEST_NAME);
+            return Err(USAGE_EXIT_CODE);
+        }
+    };
+    Manifest::load(&path).map_err(|err| {
+        eprintln!("{}", err);
+        IO_EXIT_CODE
+    })
+}
+
+/// Where the files of a project look for borrowed files: the source directories of the manifest,
+/// followed by those given on the command line.
+pub fn search_path(manifest: &Manifest, include: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut search_path = manifest.source_dirs();
+    search_path.extend(include);
+    search_path
+}
+
+/// All `.chef` files in a directory and its subdirectories, sorted by path.
+pub fn recipe_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(recipe_files(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "chef") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The main file and all files in the source directories of a project.
+pub fn project_files(manifest: &Manifest) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![manifest.main_path()];
+    for dir in manifest.source_dirs() {
+        let found = recipe_files(&dir)
+            .map_err(|err| format!("failed to read source directory {}: {}", dir.display(), err))?;
+        files.extend(found.into_iter().filter(|file| !files.contains(file)).collect::<Vec<_>>());
+    }
+    Ok(files)
+}
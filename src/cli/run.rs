@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use clap::Args;
+
+use chef_rs::ast::statement::{SpannedStatement, Statement};
+use chef_rs::coverage::{CoverageObserver, CoverageReport};
+use chef_rs::interpreter::execution::{Execution, StepResult};
+use chef_rs::interpreter::interpreter::{CookingTime, Interpreter, InterpreterOptions, InterpreterResult, RuntimeChecks};
+use chef_rs::interpreter::kitchen::{normalized_ingredients, BowlPreset, IngredientPreset, KitchenSetup, PresetValue};
+use chef_rs::interpreter::number::JunkPolicy;
+use chef_rs::interpreter::output::{DishRoute, Output, OutputFormat, SinkTarget};
+use chef_rs::interpreter::refrigerator::{EofBehavior, RefrigeratorBinding, RefrigeratorSource, Refrigerators};
+use chef_rs::manifest::Manifest;
+
+use crate::cli::checkpoint::{self, Checkpoint};
+use crate::cli::coverage::write_coverage;
+use crate::cli::{load_exit_code, load_located_recipes, load_manifest, search_path, FAILURE_EXIT_CODE, INTERRUPTED_EXIT_CODE, IO_EXIT_CODE, USAGE_EXIT_CODE};
+
+#[derive(Args)]
+#[clap(rename_all = "kebab")]
+pub struct RunOpts {
+    /// The recipe file, or - to read it from stdin [default: the main file of the nearest Cookbook.toml]
+    recipe: Option<String>,
+    /// Run the project of this manifest instead of the nearest one
+    #[clap(long, value_name = "FILE", conflicts_with = "recipe")]
+    manifest: Option<PathBuf>,
+    /// What to do when taking from an empty refrigerator: error, zero or sentinel (-1) [default: error, or sentinel with --bytes]
+    #[clap(long)]
+    eof: Option<EofBehavior>,
+    /// What to do with junk in front of a number: strict (only whitespace) or lenient (skip anything) [default: lenient]
+    #[clap(long)]
+    junk: Option<JunkPolicy>,
+    /// How to write served dishes: spec (dry values space-separated), lines (one value per line) or json [default: spec]
+    #[clap(long)]
+    format: Option<OutputFormat>,
+    /// What to do about division by zero, liquefying values that aren't characters and stirring too deep:
+    /// strict (runtime error) or lenient (saturate) [default: lenient]
+    #[clap(long)]
+    checks: Option<RuntimeChecks>,
+    /// Whether recipes burn when they cook longer than their cooking time: ignored,
+    /// steps[=<statements per minute>] (1000 by default) or clock[=<seconds per minute>] (60 by default) [default: ignored]
+    #[clap(long, value_name = "BUDGET")]
+    cooking_time: Option<CookingTime>,
+    /// Serve a baking dish somewhere else than stdout: <dish>=stdout|stderr|<file>
+    #[clap(long = "dish", value_name = "DISH=TARGET")]
+    dishes: Vec<DishRoute>,
+    /// Stock a refrigerator from somewhere else than stdin: <refrigerator>=stdin|text:<literal>|<file>
+    #[clap(long = "fridge", value_name = "REFRIGERATOR=SOURCE")]
+    refrigerators: Vec<RefrigeratorBinding>,
+    /// Write what `Examine` finds somewhere else than stderr: stdout|stderr|<file>
+    #[clap(long, value_name = "TARGET")]
+    debug: Option<SinkTarget>,
+    /// Don't write anything for `Examine`
+    #[clap(long, conflicts_with = "debug")]
+    no_examine: bool,
+    /// Take and serve liquid ingredients as raw bytes instead of Unicode characters
+    #[clap(long, overrides_with = "no-bytes")]
+    bytes: bool,
+    /// Take and serve liquid ingredients as Unicode characters, even if the manifest asks for bytes
+    #[clap(long, overrides_with = "bytes")]
+    no_bytes: bool,
+    /// Load the initial state of the kitchen from a JSON file
+    #[clap(long, value_name = "FILE")]
+    kitchen: Option<PathBuf>,
+    /// Override the initial value of an ingredient of the main recipe: <ingredient>=<value>
+    #[clap(long = "set", value_name = "INGREDIENT=VALUE")]
+    ingredients: Vec<IngredientPreset>,
+    /// Prefill a mixing bowl, listed from the bottom to the top: <bowl>=<value>,<value>,...
+    #[clap(long = "bowl", value_name = "BOWL=VALUES")]
+    bowls: Vec<BowlPreset>,
+    /// Put some text into the refrigerator that stdin stocks before reading from stdin
+    #[clap(long)]
+    input: Option<String>,
+    /// Also look for files borrowed with `Borrow recipes from` in this directory
+    #[clap(long, short = 'I', value_name = "DIR")]
+    include: Vec<PathBuf>,
+    /// Run this recipe instead of the first one in the file
+    #[clap(long, value_name = "RECIPE")]
+    entry: Option<String>,
+    /// Save a snapshot of the kitchen to this file when cooking fails
+    #[clap(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+    /// Also save a snapshot to the checkpoint file every N statements
+    #[clap(long, value_name = "N", requires = "checkpoint")]
+    checkpoint_every: Option<u64>,
+    /// Continue cooking from a snapshot instead of starting over
+    #[clap(long, value_name = "FILE")]
+    resume: Option<PathBuf>,
+    /// Write which statements have been run to this file as an lcov tracefile and print a summary to stderr
+    #[clap(long, value_name = "FILE")]
+    coverage: Option<PathBuf>,
+}
+
+/// Makes the options run the project of the manifest.
+/// Options given on the command line take precedence over the settings of the manifest.
+fn apply_manifest(opts: &mut RunOpts, manifest: &Manifest) {
+    let settings = &manifest.settings;
+    opts.recipe = Some(manifest.main_path().to_string_lossy().into_owned());
+    opts.include = search_path(manifest, std::mem::take(&mut opts.include));
+    opts.entry = opts.entry.take().or_else(|| manifest.entry.clone());
+    let bindings = manifest.input.iter().map(|(&refrigerator, source)| RefrigeratorBinding {
+        refrigerator,
+        source: match source {
+            RefrigeratorSource::File(path) => RefrigeratorSource::File(manifest.resolve(path)),
+            source => source.clone(),
+        },
+    });
+    opts.refrigerators.splice(0..0, bindings);
+    opts.eof = opts.eof.or(settings.eof);
+    opts.junk = opts.junk.or(settings.junk);
+    opts.checks = opts.checks.or(settings.checks);
+    opts.format = opts.format.or(settings.format);
+    opts.cooking_time = opts.cooking_time.or(settings.cooking_time);
+    if !opts.bytes && !opts.no_bytes {
+        opts.bytes = settings.bytes.unwrap_or_default();
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// What the kitchen looks like while a statement that may wait for input is running,
+/// so that a second Ctrl-C can still tell where cooking got stuck.
+static WAITING_KITCHEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Where cooking is and what is in the kitchen, as Ctrl-C reports it.
+fn describe_kitchen(execution: &Execution) -> String {
+    format!("{}{}", execution.backtrace().calls(), execution.examine_kitchen())
+}
+
+enum Cooked {
+    /// The recipe is done, with the exit code it has been thrown away with, if it has.
+    Finished(Option<i32>),
+    Interrupted,
+}
+
+/// Cooks until the recipe is done, taking snapshots along the way if asked to.
+/// On Ctrl-C, the state of the kitchen is dumped to stderr.
+fn cook(opts: &RunOpts, interpreter: &Interpreter, refrigerators: &mut Refrigerators, output: &mut Output) -> InterpreterResult<Cooked> {
+    let mut execution = checkpoint::start(opts.resume.as_deref(), opts.entry.as_deref(), interpreter, refrigerators, output)?;
+    let mut checkpoint = Checkpoint::new(opts.checkpoint.as_deref(), opts.checkpoint_every);
+    loop {
+        let waits = matches!(
+            execution.current_statement().map(SpannedStatement::statement),
+            Some(Statement::Read { .. } | Statement::CheckInput { .. })
+        );
+        if waits {
+            *WAITING_KITCHEN.lock().unwrap() = Some(describe_kitchen(&execution));
+        }
+        let result = execution.step().and_then(|result| match result {
+            StepResult::NeedsInput(refrigerator) => Err(format!("refrigerator {} needs more input", refrigerator).into()),
+            result => Ok(result),
+        });
+        if waits {
+            *WAITING_KITCHEN.lock().unwrap() = None;
+        }
+        match result {
+            Ok(StepResult::Finished) => return Ok(Cooked::Finished(execution.exit_code())),
+            Ok(_) => {}
+            Err(err) => {
+                if let Err(save_err) = checkpoint.save(&execution) {
+                    eprintln!("{}", save_err);
+                }
+                return Err(err);
+            }
+        }
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            eprintln!("interrupted while cooking\n{}", describe_kitchen(&execution));
+            checkpoint.save(&execution)?;
+            return Ok(Cooked::Interrupted);
+        }
+        checkpoint.step(&execution)?;
+    }
+}
+
+/// Cooks a recipe file or the project of a manifest and returns the exit code.
+pub fn run(mut opts: RunOpts) -> i32 {
+    if opts.recipe.is_none() {
+        match load_manifest(opts.manifest.take()) {
+            Ok(manifest) => apply_manifest(&mut opts, &manifest),
+            Err(code) => return code,
+        }
+    }
+    let recipe = opts.recipe.as_deref().unwrap_or_default();
+
+    let mut kitchen = match &opts.kitchen {
+        Some(path) => match KitchenSetup::load(path) {
+            Ok(kitchen) => kitchen,
+            Err(err) => {
+                eprintln!("{}", err);
+                return IO_EXIT_CODE;
+            }
+        },
+        None => KitchenSetup::default(),
+    };
+    match normalized_ingredients(opts.ingredients.iter().map(|preset| (preset.ingredient.clone(), preset.value))) {
+        Ok(ingredients) => kitchen.ingredients.extend(ingredients),
+        Err(err) => {
+            eprintln!("invalid --set: {}", err);
+            return USAGE_EXIT_CODE;
+        }
+    }
+    for preset in &opts.bowls {
+        kitchen.bowls.insert(preset.bowl, preset.values.iter().copied().map(PresetValue::Number).collect());
+    }
+    if opts.input.is_some() {
+        kitchen.input = opts.input.clone();
+    }
+
+    // A second Ctrl-C gets out even when cooking is stuck waiting for input.
+    if let Err(err) = ctrlc::set_handler(|| {
+        let waiting = WAITING_KITCHEN.lock().unwrap();
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            if let Some(kitchen) = waiting.as_ref() {
+                eprintln!("interrupted while waiting for input\n{}", kitchen);
+            }
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        } else if waiting.is_some() {
+            eprintln!("waiting for input, press Ctrl-C again to stop");
+        }
+    }) {
+        eprintln!("failed to catch Ctrl-C: {}", err);
+    }
+
+    let (recipes, locations) = match load_located_recipes(recipe, &opts.include) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            return load_exit_code(&err);
+        }
+    };
+
+    let default_eof = if opts.bytes { EofBehavior::Sentinel } else { EofBehavior::Error };
+    let options = InterpreterOptions {
+        eof_behavior: opts.eof.unwrap_or(default_eof),
+        junk_policy: opts.junk.unwrap_or_default(),
+        output_format: opts.format.unwrap_or_default(),
+        bytes: opts.bytes,
+        kitchen,
+        checks: opts.checks.unwrap_or_default(),
+        cooking_time: opts.cooking_time.unwrap_or_default(),
+    };
+    let mut refrigerators = Refrigerators::stdin(options.refrigerator_settings());
+    if let Err(err) = refrigerators.add_bindings(&opts.refrigerators) {
+        eprintln!("{}", err);
+        return IO_EXIT_CODE;
+    }
+    let mut output = Output::stdout(options.output_format, opts.bytes);
+    if let Err(err) = output.add_routes(&opts.dishes) {
+        eprintln!("failed to open dish output: {}", err);
+        return IO_EXIT_CODE;
+    }
+    if opts.no_examine {
+        output.set_debug_sink(None);
+    } else if let Some(target) = &opts.debug {
+        match target.open() {
+            Ok(sink) => output.set_debug_sink(Some(sink)),
+            Err(err) => {
+                eprintln!("failed to open debug output: {}", err);
+                return IO_EXIT_CODE;
+            }
+        }
+    }
+    let mut interpreter = Interpreter::with_options(recipes, options);
+    let observer = Rc::new(CoverageObserver::new());
+    if opts.coverage.is_some() {
+        interpreter.add_observer(observer.clone());
+    }
+    let result = cook(&opts, &interpreter, &mut refrigerators, &mut output);
+    let finished = output.finish();
+    if let Some(path) = &opts.coverage {
+        let mut report = CoverageReport::default();
+        report.add(interpreter.recipes(), &locations, &observer.coverage());
+        eprintln!("coverage:\n{}", report.summary());
+        if !write_coverage(path, &report) && result.is_ok() {
+            return IO_EXIT_CODE;
+        }
+    }
+    match result.and_then(|cooked| finished.map(|_| cooked)) {
+        Ok(Cooked::Finished(exit_code)) => exit_code.unwrap_or(0),
+        Ok(Cooked::Interrupted) => INTERRUPTED_EXIT_CODE,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            if let Some(backtrace) = err.backtrace {
+                eprintln!("{}", backtrace);
+            }
+            FAILURE_EXIT_CODE
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use chef_rs::coverage::CoverageReport;
+use chef_rs::interpreter::interpreter::InterpreterOptions;
+use chef_rs::manifest::{TestCase, MANIFEST_NAME};
+use chef_rs::testing::{self, TestJob};
+
+use crate::cli::coverage::write_coverage;
+use crate::cli::{load_manifest, project_files, recipe_files, search_path, FAILURE_EXIT_CODE, IO_EXIT_CODE};
+
+/// Collects the test cases of recipe files.
+/// Returns the number of files whose test cases couldn't be read.
+fn collect_file_tests(files: &[PathBuf], search_path: &[PathBuf], options: &InterpreterOptions, jobs: &mut Vec<TestJob>) -> usize {
+    let mut problems = 0;
+    for file in files {
+        match testing::file_cases(file) {
+            Ok(cases) => jobs.extend(cases.into_iter().map(|case| TestJob {
+                source: file.clone(),
+                recipe_file: file.clone(),
+                search_path: search_path.to_vec(),
+                options: options.clone(),
+                root: file.parent().map(Path::to_path_buf).unwrap_or_default(),
+                case,
+                coverage: false,
+            })),
+            Err(err) => {
+                eprintln!("{}", err);
+                problems += 1;
+            }
+        }
+    }
+    problems
+}
+
+/// Runs the test cases of the given recipe files and directories, or those of the project,
+/// and compares what has been served with what was expected.
+/// Returns the exit code.
+pub fn test(paths: Vec<PathBuf>, manifest: Option<PathBuf>, include: Vec<PathBuf>, threads: Option<usize>, coverage: Option<PathBuf>) -> i32 {
+    let mut jobs = Vec::new();
+    let mut problems = 0;
+    if paths.is_empty() {
+        let manifest = match load_manifest(manifest) {
+            Ok(manifest) => manifest,
+            Err(code) => return code,
+        };
+        let options = manifest.interpreter_options();
+        let search_path = search_path(&manifest, include);
+        for case in &manifest.tests {
+            jobs.push(TestJob {
+                source: manifest.resolve(Path::new(MANIFEST_NAME)),
+                recipe_file: manifest.main_path(),
+                search_path: search_path.clone(),
+                options: options.clone(),
+                root: manifest.root.clone(),
+                case: TestCase { entry: case.entry.clone().or_else(|| manifest.entry.clone()), ..case.clone() },
+                coverage: false,
+            });
+        }
+        match project_files(&manifest) {
+            Ok(files) => problems += collect_file_tests(&files, &search_path, &options, &mut jobs),
+            Err(err) => {
+                eprintln!("{}", err);
+                return IO_EXIT_CODE;
+            }
+        }
+    } else {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                match recipe_files(&path) {
+                    Ok(found) => files.extend(found),
+                    Err(err) => {
+                        eprintln!("failed to read directory {}: {}", path.display(), err);
+                        return IO_EXIT_CODE;
+                    }
+                }
+            } else {
+                files.push(path);
+            }
+        }
+        problems += collect_file_tests(&files, &include, &InterpreterOptions::default(), &mut jobs);
+    }
+
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from));
+    for job in &mut jobs {
+        job.coverage = coverage.is_some();
+    }
+    let outcomes = testing::run_tests(&jobs, threads);
+    let mut failed = 0;
+    let mut report = CoverageReport::default();
+    for (job, outcome) in jobs.iter().zip(outcomes) {
+        report.merge(outcome.coverage.unwrap_or_default());
+        match outcome.result {
+            Ok(()) => println!("test {}: {} ... ok", job.source.display(), job.case.name),
+            Err(err) => {
+                failed += 1;
+                println!("test {}: {} ... FAILED", job.source.display(), job.case.name);
+                for line in err.lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+    }
+    println!("{} passed, {} failed", jobs.len() - failed, failed);
+    if let Some(path) = &coverage {
+        println!("coverage:\n{}", report.summary());
+        if !write_coverage(path, &report) {
+            return IO_EXIT_CODE;
+        }
+    }
+    if failed == 0 && problems == 0 { 0 } else { FAILURE_EXIT_CODE }
+}
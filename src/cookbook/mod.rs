@@ -0,0 +1,126 @@
+use lazy_static::lazy_static;
+
+use crate::ast::recipe::{recipes_from, Recipes};
+use crate::grammar::parse;
+
+/// The version of the bundled cookbook. It goes up whenever a recipe is added or changes what it does.
+pub const COOKBOOK_VERSION: u32 = 1;
+
+/// An auxiliary recipe that ships with the interpreter.
+///
+/// By convention, cookbook recipes read their arguments from the top of the 2nd mixing bowl
+/// and leave only their results in the 1st mixing bowl, which ends up on top of the caller's 1st mixing bowl.
+/// Like any auxiliary recipe, they work on copies of the caller's mixing bowls, so the caller's 2nd mixing bowl keeps its arguments.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CookbookRecipe {
+    /// The title of the recipe, as `Serve with` refers to it.
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+pub const RECIPES: &[CookbookRecipe] = &[
+    CookbookRecipe { name: "Duplicated top", source: include_str!("duplicated_top.chef") },
+    CookbookRecipe { name: "Integer division", source: include_str!("integer_division.chef") },
+    CookbookRecipe { name: "Modulo", source: include_str!("modulo.chef") },
+    CookbookRecipe { name: "Number as characters", source: include_str!("number_as_characters.chef") },
+    CookbookRecipe { name: "Reversed bowl", source: include_str!("reversed_bowl.chef") },
+];
+
+/// Looks up a cookbook recipe by its name, case-insensitively.
+pub fn find(name: &str) -> Option<&'static CookbookRecipe> {
+    let name = name.trim();
+    RECIPES.iter().find(|recipe| recipe.name.eq_ignore_ascii_case(name))
+}
+
+lazy_static! {
+    static ref PARSED: Recipes = parse_recipes();
+}
+
+/// All cookbook recipes, parsed the first time they are needed.
+pub fn recipes() -> &'static Recipes {
+    &PARSED
+}
+
+fn parse_recipes() -> Recipes {
+    let mut recipes = Recipes::new();
+    for recipe in RECIPES {
+        let mut parsed = parse(recipe.source)
+            .unwrap_or_else(|err| panic!("cookbook recipe {} does not parse: {}", recipe.name, err));
+        let parsed = recipes_from(parsed.next().unwrap())
+            .unwrap_or_else(|err| panic!("cookbook recipe {} is broken: {:?}", recipe.name, err));
+        recipes.extend(parsed);
+    }
+    recipes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::output::{MemorySink, Output, OutputFormat};
+    use crate::interpreter::refrigerator::{RefrigeratorSettings, Refrigerators};
+
+    use super::*;
+
+    const ARGUMENTS: [&str; 4] = ["first", "second", "third", "fourth"];
+
+    /// Puts the arguments into the 2nd mixing bowl, serves the cookbook recipe and serves the 1st mixing bowl
+    /// in the 1st baking dish and the 2nd mixing bowl in the 2nd baking dish.
+    fn serve(recipe: &str, arguments: &[f64]) -> String {
+        let ingredients: Vec<_> = arguments.iter().zip(ARGUMENTS)
+            .map(|(value, name)| format!("{} g {}\n", value, name))
+            .collect();
+        let puts: Vec<_> = ARGUMENTS[..arguments.len()].iter()
+            .map(|name| format!("Put {} into the 2nd mixing bowl.\n", name))
+            .collect();
+        let code = format!("Caller.\n\nIngredients.\n{}\nMethod.\n{}Serve with {}.\n\
+            Pour contents of the mixing bowl into the baking dish.\n\
+            Pour contents of the 2nd mixing bowl into the 2nd baking dish.\n\nServes 2.\n",
+            ingredients.concat(), puts.concat(), recipe);
+        let recipes = recipes_from(parse(&code).unwrap().next().unwrap()).unwrap();
+        let interpreter = Interpreter::new(recipes);
+        let mut refrigerators = Refrigerators::new(RefrigeratorSettings::default());
+        let served = MemorySink::default();
+        let mut output = Output::new(Box::new(served.clone()), OutputFormat::Spec, false);
+        interpreter.run_main_with(&mut refrigerators, &mut output).unwrap();
+        String::from_utf8(served.contents()).unwrap()
+    }
+
+    #[test]
+    fn recipes_are_parsed_once() {
+        assert!(std::ptr::eq(recipes(), recipes()));
+        assert_eq!(recipes().len(), RECIPES.len());
+        for recipe in RECIPES {
+            assert!(recipes().contains_key(&recipe.name.to_lowercase()), "{} is missing", recipe.name);
+        }
+    }
+
+    #[test]
+    fn integer_division() {
+        assert_eq!(serve("Integer division", &[17.0, 5.0]), "3\n5 17\n");
+        assert_eq!(serve("Integer division", &[15.0, 5.0]), "3\n5 15\n");
+        assert_eq!(serve("Integer division", &[4.0, 5.0]), "0\n5 4\n");
+    }
+
+    #[test]
+    fn modulo() {
+        assert_eq!(serve("Modulo", &[17.0, 5.0]), "2\n5 17\n");
+        assert_eq!(serve("Modulo", &[15.0, 5.0]), "0\n5 15\n");
+        assert_eq!(serve("Modulo", &[4.0, 5.0]), "4\n5 4\n");
+    }
+
+    #[test]
+    fn number_as_characters() {
+        assert_eq!(serve("Number as characters", &[405.0]), "405\n405\n");
+        assert_eq!(serve("Number as characters", &[0.0]), "0\n0\n");
+    }
+
+    #[test]
+    fn reversed_bowl() {
+        assert_eq!(serve("Reversed bowl", &[1.0, 2.0, 3.0, 2.0]), "2 3\n2 3 2 1\n");
+    }
+
+    #[test]
+    fn duplicated_top() {
+        assert_eq!(serve("Duplicated top", &[1.0, 7.0]), "7 7\n7 1\n");
+    }
+}
@@ -0,0 +1,388 @@
+//! Statement coverage: which sentences of a method ran, how often the bodies of loops ran and which recipes were served.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::ast::recipe::Recipes;
+use crate::ast::statement::{SpannedStatement, Statement};
+use crate::ast::util::Span;
+use crate::interpreter::observer::InterpreterObserver;
+use crate::loader::RecipeLocation;
+
+/// How often things have been run while cooking, by recipe name and the position of the statement.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    /// How often each statement has been started. Loops count once per run, not per iteration.
+    pub statements: HashMap<(String, Span), u64>,
+    /// How often the body of each loop has run.
+    pub loop_bodies: HashMap<(String, Span), u64>,
+    /// How often each recipe has been served, or cooked as the main recipe.
+    pub recipes: HashMap<String, u64>,
+}
+
+/// Records [`Coverage`] while cooking.
+#[derive(Debug, Default)]
+pub struct CoverageObserver {
+    coverage: RefCell<Coverage>,
+}
+
+impl CoverageObserver {
+    pub fn new() -> CoverageObserver {
+        CoverageObserver::default()
+    }
+
+    /// What has been recorded so far.
+    pub fn coverage(&self) -> Coverage {
+        self.coverage.borrow().clone()
+    }
+}
+
+impl InterpreterObserver for CoverageObserver {
+    fn recipe_entered(&self, recipe: &str) {
+        *self.coverage.borrow_mut().recipes.entry(recipe.to_string()).or_insert(0) += 1;
+    }
+
+    fn statement_started(&self, recipe: &str, statement: &SpannedStatement) {
+        *self.coverage.borrow_mut().statements.entry((recipe.to_string(), statement.span())).or_insert(0) += 1;
+    }
+
+    fn loop_iterated(&self, recipe: &str, statement: &SpannedStatement, _iteration: usize) {
+        *self.coverage.borrow_mut().loop_bodies.entry((recipe.to_string(), statement.span())).or_insert(0) += 1;
+    }
+}
+
+/// The coverage of a single recipe.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecipeCoverage {
+    /// Where the recipe is in its file.
+    pub span: Span,
+    /// How often the recipe has been served, or cooked as the main recipe.
+    pub served: u64,
+    /// How often each statement has been started, including those of loop bodies.
+    pub statements: BTreeMap<Span, u64>,
+    /// How often the body of each loop has run.
+    pub loops: BTreeMap<Span, LoopCoverage>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoopCoverage {
+    pub verb: String,
+    pub bodies: u64,
+}
+
+impl RecipeCoverage {
+    fn merge(&mut self, other: RecipeCoverage) {
+        self.served += other.served;
+        for (span, count) in other.statements {
+            *self.statements.entry(span).or_insert(0) += count;
+        }
+        for (span, looped) in other.loops {
+            self.loops.entry(span)
+                .or_insert_with(|| LoopCoverage { verb: looped.verb.clone(), bodies: 0 })
+                .bodies += looped.bodies;
+        }
+    }
+
+    fn add_statements(&mut self, recipe: &str, statements: &[SpannedStatement], coverage: &Coverage) {
+        for statement in statements {
+            let key = (recipe.to_string(), statement.span());
+            *self.statements.entry(statement.span()).or_insert(0) += coverage.statements.get(&key).copied().unwrap_or(0);
+            if let Statement::Loop { verb, statements, .. } = statement.statement() {
+                self.loops.entry(statement.span())
+                    .or_insert_with(|| LoopCoverage { verb: verb.clone(), bodies: 0 })
+                    .bodies += coverage.loop_bodies.get(&key).copied().unwrap_or(0);
+                self.add_statements(recipe, statements, coverage);
+            }
+        }
+    }
+
+    /// How often each line with statements has run: as often as the statement on it that has run the least.
+    fn lines(&self) -> BTreeMap<usize, u64> {
+        let mut lines = BTreeMap::new();
+        for (span, count) in &self.statements {
+            let line = lines.entry(span.begin.0).or_insert(*count);
+            *line = (*line).min(*count);
+        }
+        lines
+    }
+}
+
+/// The coverage of recipe files, combined from any number of runs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    /// The recipes by their file and name.
+    pub files: BTreeMap<PathBuf, BTreeMap<String, RecipeCoverage>>,
+}
+
+impl CoverageReport {
+    /// Adds what has been recorded while cooking the given recipes.
+    /// Recipes without a location, like those of the cookbook, are left out.
+    pub fn add(&mut self, recipes: &Recipes, locations: &HashMap<String, RecipeLocation>, coverage: &Coverage) {
+        for (name, recipe) in recipes {
+            let location = match locations.get(name) {
+                Some(location) => location,
+                None => continue,
+            };
+            let mut recipe_coverage = RecipeCoverage {
+                span: location.span,
+                served: coverage.recipes.get(name).copied().unwrap_or(0),
+                ..RecipeCoverage::default()
+            };
+            recipe_coverage.add_statements(name, recipe.statements(), coverage);
+            self.add_recipe(location.path.clone(), name.clone(), recipe_coverage);
+        }
+    }
+
+    /// Adds the coverage of another report, e.g. of another run.
+    pub fn merge(&mut self, other: CoverageReport) {
+        for (path, recipes) in other.files {
+            for (name, recipe_coverage) in recipes {
+                self.add_recipe(path.clone(), name, recipe_coverage);
+            }
+        }
+    }
+
+    fn add_recipe(&mut self, path: PathBuf, name: String, recipe_coverage: RecipeCoverage) {
+        let recipes = self.files.entry(path).or_default();
+        match recipes.get_mut(&name) {
+            Some(existing) => existing.merge(recipe_coverage),
+            None => {
+                recipes.insert(name, recipe_coverage);
+            }
+        }
+    }
+
+    /// The recipes of a file in the order they are defined in.
+    fn sorted(recipes: &BTreeMap<String, RecipeCoverage>) -> Vec<(&String, &RecipeCoverage)> {
+        let mut sorted: Vec<_> = recipes.iter().collect();
+        sorted.sort_by_key(|(_, recipe_coverage)| recipe_coverage.span);
+        sorted
+    }
+
+    /// The report in the lcov tracefile format.
+    /// Recipes are reported as functions, loops as branches that are taken as often as their bodies ran,
+    /// and every line with statements as often as the statement on it that has run the least.
+    pub fn lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, recipes) in &self.files {
+            let recipes = Self::sorted(recipes);
+            lcov.push_str(&format!("TN:\nSF:{}\n", path.display()));
+            for (name, recipe_coverage) in &recipes {
+                lcov.push_str(&format!("FN:{},{}\n", recipe_coverage.span.begin.0, name));
+            }
+            for (name, recipe_coverage) in &recipes {
+                lcov.push_str(&format!("FNDA:{},{}\n", recipe_coverage.served, name));
+            }
+            let served = recipes.iter().filter(|(_, recipe_coverage)| recipe_coverage.served > 0).count();
+            lcov.push_str(&format!("FNF:{}\nFNH:{}\n", recipes.len(), served));
+
+            let (mut branches, mut taken) = (0, 0);
+            for (_, recipe_coverage) in &recipes {
+                for (span, looped) in &recipe_coverage.loops {
+                    let reached = recipe_coverage.statements.get(span).copied().unwrap_or(0) > 0;
+                    let bodies = if reached { looped.bodies.to_string() } else { String::from("-") };
+                    lcov.push_str(&format!("BRDA:{},{},0,{}\n", span.begin.0, branches, bodies));
+                    branches += 1;
+                    if looped.bodies > 0 {
+                        taken += 1;
+                    }
+                }
+            }
+            lcov.push_str(&format!("BRF:{}\nBRH:{}\n", branches, taken));
+
+            let mut lines = BTreeMap::new();
+            for (_, recipe_coverage) in &recipes {
+                lines.extend(recipe_coverage.lines());
+            }
+            for (line, count) in &lines {
+                lcov.push_str(&format!("DA:{},{}\n", line, count));
+            }
+            let hit = lines.values().filter(|count| **count > 0).count();
+            lcov.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), hit));
+        }
+        lcov
+    }
+
+    /// A summary per recipe: how many of its statements ran, how often it has been served,
+    /// where the statements are that never ran and how often the bodies of its loops ran.
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        for (path, recipes) in &self.files {
+            lines.push(format!("{}:", path.display()));
+            for (name, recipe_coverage) in Self::sorted(recipes) {
+                let total = recipe_coverage.statements.len();
+                let run = recipe_coverage.statements.values().filter(|count| **count > 0).count();
+                let percent = (run * 100).checked_div(total).unwrap_or(100);
+                let served = match recipe_coverage.served {
+                    0 => String::from("never served"),
+                    1 => String::from("served once"),
+                    served => format!("served {} times", served),
+                };
+                lines.push(format!("  {} at {}: {} of {} statements run ({}%), {}", name, recipe_coverage.span, run, total, percent, served));
+                let not_run: Vec<_> = recipe_coverage.statements.iter()
+                    .filter(|(_, count)| **count == 0)
+                    .map(|(span, _)| span.to_string())
+                    .collect();
+                if !not_run.is_empty() {
+                    lines.push(format!("    not run: {}", not_run.join(", ")));
+                }
+                for (span, looped) in &recipe_coverage.loops {
+                    lines.push(format!("    {} loop at {}: body ran {} {}", looped.verb, span, looped.bodies, if looped.bodies == 1 { "time" } else { "times" }));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::output::{MemorySink, Output, OutputFormat};
+    use crate::interpreter::refrigerator::{RefrigeratorSettings, Refrigerators};
+    use crate::loader::Loader;
+
+    use super::*;
+
+    const RECIPES: &str = "Main.
+
+Ingredients.
+3 g count
+0 g flag
+
+Method.
+Sift the count.
+Put count into the mixing bowl.
+Sift the count until sifted.
+Mash the flag.
+Serve with unused.
+Mash the flag until mashed.
+Serve with helper.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Unused.
+
+Ingredients.
+1 g one
+
+Method.
+Put one into the mixing bowl.
+
+Serves 1.
+
+Helper.
+
+Ingredients.
+1 g one
+
+Method.
+Add one to the mixing bowl.
+";
+
+    fn report() -> CoverageReport {
+        let (recipes, locations) = Loader::new(Vec::new()).load_source_located(Path::new("main.chef"), RECIPES).unwrap();
+        let observer = Rc::new(CoverageObserver::new());
+        let mut interpreter = Interpreter::new(recipes);
+        interpreter.add_observer(observer.clone());
+        let mut refrigerators = Refrigerators::new(RefrigeratorSettings::default());
+        let mut output = Output::new(Box::new(MemorySink::default()), OutputFormat::Spec, false);
+        output.set_debug_sink(None);
+        interpreter.run_main_with(&mut refrigerators, &mut output).unwrap();
+        let mut report = CoverageReport::default();
+        report.add(interpreter.recipes(), &locations, &observer.coverage());
+        report
+    }
+
+    fn recipe<'a>(report: &'a CoverageReport, name: &str) -> &'a RecipeCoverage {
+        &report.files[Path::new("main.chef")][name]
+    }
+
+    fn count_at(recipe_coverage: &RecipeCoverage, line: usize) -> u64 {
+        recipe_coverage.statements.iter()
+            .find(|(span, _)| span.begin.0 == line)
+            .map(|(_, count)| *count)
+            .unwrap_or_else(|| panic!("no statement on line {}", line))
+    }
+
+    #[test]
+    fn statements_are_counted_as_they_run() {
+        let report = report();
+        let main = recipe(&report, "main");
+        assert_eq!(count_at(main, 8), 1);
+        assert_eq!(count_at(main, 9), 3);
+        assert_eq!(count_at(main, 11), 1);
+        assert_eq!(count_at(main, 12), 0);
+        assert_eq!(count_at(main, 14), 1);
+        assert_eq!(count_at(main, 17), 1);
+        assert_eq!(count_at(recipe(&report, "helper"), 35), 1);
+    }
+
+    #[test]
+    fn loop_bodies_are_counted_per_iteration() {
+        let report = report();
+        let loops: Vec<_> = recipe(&report, "main").loops.iter()
+            .map(|(span, looped)| (span.begin.0, looped.verb.as_str(), looped.bodies))
+            .collect();
+        assert_eq!(loops, vec![(8, "sift", 3), (11, "mash", 0)]);
+    }
+
+    #[test]
+    fn recipes_that_are_never_served_are_kept() {
+        let report = report();
+        let unused = recipe(&report, "unused");
+        assert_eq!(unused.served, 0);
+        assert_eq!(unused.statements.values().collect::<Vec<_>>(), vec![&0, &0]);
+        assert_eq!(recipe(&report, "main").served, 1);
+        assert_eq!(recipe(&report, "helper").served, 1);
+    }
+
+    #[test]
+    fn lcov_records() {
+        assert_eq!(report().lcov(), "TN:
+SF:main.chef
+FN:1,main
+FN:19,unused
+FN:29,helper
+FNDA:1,main
+FNDA:0,unused
+FNDA:1,helper
+FNF:3
+FNH:2
+BRDA:8,0,0,3
+BRDA:11,1,0,0
+BRF:2
+BRH:1
+DA:8,1
+DA:9,3
+DA:11,1
+DA:12,0
+DA:14,1
+DA:15,1
+DA:17,1
+DA:25,0
+DA:27,0
+DA:35,1
+LF:10
+LH:7
+end_of_record
+");
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(report().summary(), "main.chef:
+  main at 1:1: 6 of 7 statements run (85%), served once
+    not run: 12:1
+    sift loop at 8:1: body ran 3 times
+    mash loop at 11:1: body ran 0 times
+  unused at 19:1: 0 of 2 statements run (0%), never served
+    not run: 25:1, 27:1
+  helper at 29:1: 1 of 1 statements run (100%), served once");
+    }
+}
@@ -0,0 +1,125 @@
+use pest::error::ErrorVariant;
+use pest::iterators::Pair;
+use pest::Position;
+
+use crate::grammar::{parse, Rule};
+
+/// How far the statements inside a loop are indented, per loop.
+const INDENT: &str = "    ";
+
+/// Lays out Chef source the canonical way: one blank line between the sections of a recipe,
+/// one ingredient and one statement per line, loop bodies indented and one blank line between recipes.
+///
+/// Only whitespace is changed, so the recipes mean exactly the same as before.
+/// Fails if the source doesn't parse, or if laying it out would change more than that.
+pub fn format(source: &str) -> Result<String, Box<pest::error::Error<Rule>>> {
+    let recipes = parse(source)?.next().unwrap();
+    let mut formatted = String::new();
+    for pair in recipes.into_inner() {
+        match pair.as_rule() {
+            Rule::borrowLine => {
+                formatted.push_str(pair.as_str());
+                formatted.push('\n');
+            }
+            Rule::recipe => {
+                if !formatted.is_empty() {
+                    formatted.push('\n');
+                }
+                format_recipe(pair, &mut formatted);
+            }
+            _ => {}
+        }
+    }
+
+    if parse(&formatted).is_err() || !source.split_whitespace().eq(formatted.split_whitespace()) {
+        return Err(Box::new(pest::error::Error::new_from_pos(
+            ErrorVariant::CustomError { message: String::from("formatting would change more than whitespace") },
+            Position::from_start(source),
+        )));
+    }
+    Ok(formatted)
+}
+
+fn format_recipe(recipe: Pair<Rule>, formatted: &mut String) {
+    let text = recipe.as_str();
+    let start = recipe.as_span().start();
+    let mut end = start;
+    for pair in recipe.into_inner() {
+        end = pair.as_span().end();
+        match pair.as_rule() {
+            Rule::recipeName => {
+                formatted.push_str(pair.as_str());
+                formatted.push_str(".\n\n");
+            }
+            Rule::recipeComment => {
+                let mut blank = false;
+                for line in pair.as_str().trim_end().lines().map(str::trim_end) {
+                    if line.is_empty() {
+                        blank = true;
+                        continue;
+                    }
+                    if blank {
+                        formatted.push('\n');
+                        blank = false;
+                    }
+                    formatted.push_str(line);
+                    formatted.push('\n');
+                }
+                formatted.push('\n');
+            }
+            Rule::ingredientList => {
+                formatted.push_str("Ingredients.\n");
+                for definition in pair.into_inner() {
+                    push_words(definition.as_str(), formatted);
+                    formatted.push('\n');
+                }
+                formatted.push('\n');
+            }
+            Rule::cookingTime | Rule::ovenTemperature => {
+                formatted.push_str(pair.as_str());
+                formatted.push_str("\n\n");
+            }
+            Rule::method => {
+                formatted.push_str("Method.\n");
+                for statement in pair.into_inner() {
+                    if statement.as_rule() == Rule::servesStatement {
+                        formatted.push('\n');
+                    }
+                    format_statement(statement, 0, formatted);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let delimiter = text[end - start..].trim();
+    if !delimiter.is_empty() {
+        formatted.push_str(delimiter);
+        formatted.push('\n');
+    }
+}
+
+fn format_statement(statement: Pair<Rule>, depth: usize, formatted: &mut String) {
+    if statement.as_rule() == Rule::loopBlock {
+        for inner in statement.into_inner() {
+            match inner.as_rule() {
+                Rule::loopBeginStatement | Rule::loopEndStatement => format_statement(inner, depth, formatted),
+                _ => format_statement(inner, depth + 1, formatted),
+            }
+        }
+    } else {
+        formatted.push_str(&INDENT.repeat(depth));
+        formatted.push_str(statement.as_str());
+        formatted.push('\n');
+    }
+}
+
+/// Writes text with all runs of whitespace collapsed into single spaces.
+fn push_words(text: &str, formatted: &mut String) {
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            formatted.push(' ');
+        }
+        formatted.push_str(word);
+    }
+}
@@ -1,3 +1,5 @@
+mod format;
 mod parser;
 
-pub use parser::{ChefParser, Rule};
+pub use format::format;
+pub use parser::{parse, ChefParser, Rule};
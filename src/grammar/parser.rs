@@ -5,6 +5,6 @@ use pest::Parser;
 #[grammar = "grammar/chef.pest"]
 pub struct ChefParser;
 
-pub fn parse(input: &str) -> Result<Pairs<Rule>, pest::error::Error<Rule>> {
-    return ChefParser::parse(Rule::recipe, input)
+pub fn parse(input: &str) -> Result<Pairs<'_, Rule>, Box<pest::error::Error<Rule>>> {
+    ChefParser::parse(Rule::recipes, input).map_err(Box::new)
 }
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::ast::util::{MixingBowlId, Span};
+use crate::interpreter::interpreter::MixingBowl;
+
+/// The state of the kitchen when an error happened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backtrace {
+    /// The recipes on the call stack, the failing one first.
+    pub frames: Vec<BacktraceFrame>,
+    /// The non-empty mixing bowls of the failing recipe.
+    pub mixing_bowls: BTreeMap<MixingBowlId, MixingBowl>,
+}
+
+/// A recipe on the call stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BacktraceFrame {
+    pub recipe: String,
+    /// The failing statement in the failing recipe, the `Serve with` call in all others.
+    pub span: Option<Span>,
+    /// The loops that were running in this recipe, the innermost first.
+    pub loops: Vec<LoopTrace>,
+}
+
+/// A running loop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoopTrace {
+    pub verb: String,
+    pub span: Span,
+    /// The running iteration, starting at 1.
+    pub iteration: usize,
+}
+
+impl Backtrace {
+    /// Only the recipes on the call stack and their running loops, one per line.
+    pub fn calls(&self) -> Calls<'_> {
+        Calls(&self.frames)
+    }
+}
+
+pub struct Calls<'a>(&'a [BacktraceFrame]);
+
+impl Display for Calls<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (depth, frame) in self.0.iter().enumerate() {
+            match frame.span {
+                Some(span) if depth == 0 => writeln!(f, "  in {} at {}", frame.recipe, span)?,
+                Some(span) => writeln!(f, "  served from {} at {}", frame.recipe, span)?,
+                None => writeln!(f, "  in {}", frame.recipe)?,
+            }
+            for looped in &frame.loops {
+                writeln!(f, "    in iteration {} of the {} loop at {}", looped.iteration, looped.verb, looped.span)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "kitchen backtrace:")?;
+        write!(f, "{}", self.calls())?;
+        if self.mixing_bowls.is_empty() {
+            write!(f, "all mixing bowls are empty")
+        } else {
+            write!(f, "mixing bowls, from the bottom to the top:")?;
+            for (id, mixing_bowl) in &self.mixing_bowls {
+                write!(f, "\n  {}: ", id)?;
+                for (i, ingredient) in mixing_bowl.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if ingredient.liquid {
+                        write!(f, "{:?}", ingredient.as_char())?;
+                    } else {
+                        write!(f, "{}", ingredient.value)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
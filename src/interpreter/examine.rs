@@ -0,0 +1,43 @@
+use crate::ast::util::{BakingDishId, MixingBowlId, RefrigeratorId};
+use crate::interpreter::ingredient::Ingredient;
+
+/// Describes an ingredient the way `Examine` does.
+pub fn ingredient(name: &str, ingredient: Option<&Ingredient>) -> String {
+    match ingredient {
+        Some(ingredient) => format!("There is {} of {}{}", ingredient.value, name, if ingredient.liquid { " (liquid)" } else { "" }),
+        None => format!("{} does not exist.", name),
+    }
+}
+
+/// Describes a mixing bowl the way `Examine` does, listing its contents from the bottom to the top.
+pub fn mixing_bowl(id: MixingBowlId, contents: Option<&[Ingredient]>) -> String {
+    container("Mixing bowl", "mixing bowl", id, contents)
+}
+
+/// Describes a baking dish the way `Examine` does, listing its contents from the bottom to the top.
+pub fn baking_dish(id: BakingDishId, contents: Option<&[Ingredient]>) -> String {
+    container("Baking dish", "baking dish", id, contents)
+}
+
+/// Describes what is left of the current line in a refrigerator the way `Examine` does.
+pub fn refrigerator(id: RefrigeratorId, unread: Option<&[u8]>) -> String {
+    match unread {
+        Some([]) => format!("Refrigerator {} is empty.", id),
+        Some(unread) => format!("Refrigerator {} holds {:?}.", id, String::from_utf8_lossy(unread)),
+        None => format!("Refrigerator {} is not stocked.", id),
+    }
+}
+
+fn container(title: &str, name: &str, id: u8, contents: Option<&[Ingredient]>) -> String {
+    match contents {
+        Some([]) => format!("{} {} is empty.", title, id),
+        Some(contents) => format!("Content of {} {}:{}", name, id, contents.iter()
+            .map(|val| format!("\n    {} (liquid: {})", val.value, val.liquid))
+            .fold(String::new(), |mut a, b| {
+                a.push_str(&b);
+                a
+            })
+        ),
+        None => format!("{} {} does not exist.", title, id),
+    }
+}
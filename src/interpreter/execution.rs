@@ -0,0 +1,1277 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use crate::ast::recipe::Recipe;
+use crate::ast::statement::{SpannedStatement, Statement};
+use crate::ast::util::{BakingDishId, MixingBowlId, RefrigeratorId};
+use crate::interpreter::backtrace::{Backtrace, BacktraceFrame, LoopTrace};
+use crate::interpreter::examine;
+use crate::interpreter::ingredient::Ingredient;
+use crate::interpreter::interpreter::{BakingDish, BakingDishes, CookingTime, Ingredients, Interpreter, InterpreterError, InterpreterResult, MixingBowl, MixingBowls, RuntimeChecks};
+use crate::interpreter::output::Output;
+use crate::interpreter::refrigerator::Refrigerators;
+use crate::interpreter::snapshot::{FrameSnapshot, Snapshot, SNAPSHOT_VERSION};
+
+/// What happened in a single step of an [`Execution`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StepResult {
+    /// A statement has been executed and there are more to come.
+    Running,
+    /// The given refrigerator is stocked by hand and has to be [supplied](crate::interpreter::refrigerator::Refrigerator::supply)
+    /// with more input before stepping on.
+    NeedsInput(RefrigeratorId),
+    /// The recipe has been cooked.
+    Finished,
+}
+
+/// A recipe that is being cooked one statement at a time.
+///
+/// Auxiliary recipes get their own frame on a call stack instead of recursing,
+/// so execution can be paused after any statement and picked up again later.
+pub struct Execution<'a> {
+    interpreter: &'a Interpreter,
+    refrigerators: &'a mut Refrigerators,
+    output: &'a mut Output,
+    mixing_bowls: MixingBowls,
+    baking_dishes: BakingDishes,
+    frames: Vec<Frame<'a>>,
+    starved: Option<RefrigeratorId>,
+    result: Option<MixingBowl>,
+    exit_code: Option<i32>,
+}
+
+/// A recipe on the call stack.
+struct Frame<'a> {
+    recipe: &'a Recipe,
+    ingredients: Ingredients,
+    /// The method and the bodies of the loops that are currently running, innermost last.
+    blocks: Vec<Block<'a>>,
+    /// The number of statements executed since the recipe was entered, including those of auxiliary recipes.
+    steps: u64,
+    /// When the recipe was entered, or resumed.
+    entered: Instant,
+}
+
+/// A list of statements that is being worked through.
+struct Block<'a> {
+    statements: &'a [SpannedStatement],
+    /// The index of the next statement to execute.
+    position: usize,
+    /// The loop this block is the body of, `None` for the method itself.
+    looped: Option<&'a SpannedStatement>,
+    /// The running iteration of the loop, starting at 1.
+    iteration: usize,
+}
+
+/// What to do after executing a statement.
+enum Flow<'a> {
+    Next,
+    Starved(RefrigeratorId),
+    Loop(&'a [SpannedStatement]),
+    Call(&'a Recipe),
+    Break,
+    Return,
+    Exit(i32),
+}
+
+impl<'a> Execution<'a> {
+    /// Starts cooking a recipe on the given mixing bowls and baking dishes.
+    /// `initial_values` replace the initial values of the recipe's ingredients.
+    pub fn new(interpreter: &'a Interpreter, recipe_name: &str, mixing_bowls: BTreeMap<MixingBowlId, MixingBowl>, baking_dishes: BTreeMap<BakingDishId, BakingDish>, initial_values: &HashMap<String, f64>, refrigerators: &'a mut Refrigerators, output: &'a mut Output) -> InterpreterResult<Execution<'a>> {
+        let recipe = interpreter.recipe(recipe_name)
+            .ok_or_else(|| format!("unknown recipe: {}", recipe_name))?;
+        if let Some(unknown) = initial_values.keys().find(|name| !recipe.ingredients().definitions().iter().any(|def| def.name() == *name)) {
+            return Err(format!("recipe {} has no ingredient {}", recipe_name, unknown).into());
+        }
+
+        let mut execution = Execution {
+            interpreter,
+            refrigerators,
+            output,
+            mixing_bowls: MixingBowls::new(mixing_bowls, |_| MixingBowl::new()),
+            baking_dishes: BakingDishes::new(baking_dishes, |_| BakingDish::new()),
+            frames: Vec::new(),
+            starved: None,
+            result: None,
+            exit_code: None,
+        };
+        execution.enter(recipe, initial_values);
+        execution.settle()?;
+        Ok(execution)
+    }
+
+    /// Continues cooking where a snapshot has been taken.
+    /// The refrigerators get back whatever was left in them.
+    pub fn resume(interpreter: &'a Interpreter, snapshot: &Snapshot, refrigerators: &'a mut Refrigerators, output: &'a mut Output) -> InterpreterResult<Execution<'a>> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(format!("snapshot has version {}, expected {}", snapshot.version, SNAPSHOT_VERSION).into());
+        }
+        let layers = snapshot.frames.len().max(1);
+        if snapshot.mixing_bowls.len() != layers || snapshot.baking_dishes.len() != layers {
+            return Err("snapshot does not have a layer of mixing bowls and baking dishes per recipe".into());
+        }
+        let frames: Vec<Frame> = snapshot.frames.iter()
+            .map(|frame| Self::restore_frame(interpreter, frame))
+            .collect::<InterpreterResult<_>>()?;
+        for (caller, callee) in frames.iter().zip(frames.iter().skip(1)) {
+            let block = caller.blocks.last().unwrap();
+            match block.statements.get(block.position).map(|statement| statement.statement()) {
+                Some(Statement::CallAuxiliary { recipe }) if recipe == callee.recipe.name() => {}
+                _ => return Err(format!("snapshot position {} in recipe {} does not serve {}", block.position, caller.recipe.name(), callee.recipe.name()).into()),
+            }
+        }
+
+        refrigerators.restore(&snapshot.refrigerators);
+        let mut execution = Execution {
+            interpreter,
+            refrigerators,
+            output,
+            mixing_bowls: MixingBowls::from_layers(snapshot.mixing_bowls.clone(), |_| MixingBowl::new()),
+            baking_dishes: BakingDishes::from_layers(snapshot.baking_dishes.clone(), |_| BakingDish::new()),
+            frames,
+            starved: snapshot.starved,
+            result: None,
+            exit_code: None,
+        };
+        execution.settle()?;
+        Ok(execution)
+    }
+
+    /// Captures the current state, so cooking can be [resumed](Execution::resume) later.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            mixing_bowls: self.mixing_bowls.layers().to_vec(),
+            baking_dishes: self.baking_dishes.layers().to_vec(),
+            frames: self.frames.iter()
+                .map(|frame| FrameSnapshot {
+                    recipe: frame.recipe.name().clone(),
+                    ingredients: frame.ingredients.iter()
+                        .map(|(name, ingredient)| (name.clone(), ingredient.clone()))
+                        .collect(),
+                    positions: frame.blocks.iter().map(|block| block.position).collect(),
+                    iterations: frame.blocks.iter().map(|block| block.iteration).collect(),
+                    steps: frame.steps,
+                })
+                .collect(),
+            refrigerators: self.refrigerators.snapshot(),
+            starved: self.starved,
+        }
+    }
+
+    fn restore_frame(interpreter: &'a Interpreter, snapshot: &FrameSnapshot) -> InterpreterResult<Frame<'a>> {
+        let recipe = interpreter.recipe(&snapshot.recipe)
+            .ok_or_else(|| format!("snapshot refers to an unknown recipe: {}", snapshot.recipe))?;
+        if snapshot.positions.is_empty() {
+            return Err(format!("snapshot has no position in recipe {}", snapshot.recipe).into());
+        }
+
+        let mut blocks = Vec::new();
+        let mut statements = recipe.statements().as_slice();
+        let mut looped = None;
+        for (depth, position) in snapshot.positions.iter().enumerate() {
+            if *position > statements.len() {
+                return Err(format!("snapshot position {} is out of bounds in recipe {}", position, snapshot.recipe).into());
+            }
+            blocks.push(Block {
+                statements,
+                position: *position,
+                looped,
+                iteration: snapshot.iterations.get(depth).copied().unwrap_or(1),
+            });
+            if depth + 1 < snapshot.positions.len() {
+                let statement = statements.get(*position);
+                match statement.map(|statement| statement.statement()) {
+                    Some(Statement::Loop { statements: body, .. }) => {
+                        statements = body;
+                        looped = statement;
+                    }
+                    _ => return Err(format!("snapshot position {} in recipe {} is not a loop", position, snapshot.recipe).into()),
+                }
+            }
+        }
+
+        Ok(Frame {
+            recipe,
+            ingredients: snapshot.ingredients.iter()
+                .map(|(name, ingredient)| (name.clone(), ingredient.clone()))
+                .collect(),
+            blocks,
+            steps: snapshot.steps,
+            entered: Instant::now(),
+        })
+    }
+
+    /// Executes the next statement.
+    /// Errors carry a [`Backtrace`] of the kitchen at the time they happened.
+    pub fn step(&mut self) -> InterpreterResult<StepResult> {
+        self.step_statement().map_err(|mut err| {
+            if err.backtrace.is_none() {
+                err.backtrace = Some(Box::new(self.backtrace()));
+            }
+            err
+        })
+    }
+
+    /// The call stack with the running loops and the non-empty mixing bowls of the current recipe.
+    pub fn backtrace(&self) -> Backtrace {
+        Backtrace {
+            frames: self.frames.iter().rev()
+                .map(|frame| BacktraceFrame {
+                    recipe: frame.recipe.name().clone(),
+                    span: frame.blocks.last()
+                        .and_then(|block| block.statements.get(block.position))
+                        .map(|statement| statement.span()),
+                    loops: frame.blocks.iter().rev()
+                        .filter_map(|block| block.looped.map(|looped| (looped, block.iteration)))
+                        .map(|(looped, iteration)| LoopTrace {
+                            verb: match looped.statement() {
+                                Statement::Loop { verb, .. } => verb.clone(),
+                                _ => String::new(),
+                            },
+                            span: looped.span(),
+                            iteration,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            mixing_bowls: self.mixing_bowls.visible().into_iter()
+                .filter(|(_, mixing_bowl)| !mixing_bowl.is_empty())
+                .map(|(id, mixing_bowl)| (*id, mixing_bowl.clone()))
+                .collect(),
+        }
+    }
+
+    fn step_statement(&mut self) -> InterpreterResult<StepResult> {
+        let statement = match self.current_statement() {
+            Some(statement) => statement,
+            None => return Ok(StepResult::Finished),
+        };
+        let recipe = self.frames.last().unwrap().recipe;
+        if self.starved.is_none() {
+            self.interpreter.notify(|observer| observer.statement_started(recipe.name(), statement));
+            for frame in self.frames.iter_mut() {
+                frame.steps += 1;
+            }
+        }
+        self.starved = None;
+        self.check_cooking_time()
+            .map_err(|err| err.at(recipe.name(), statement.span()))?;
+
+        let flow = self.execute(statement.statement())
+            .map_err(|err| err.at(recipe.name(), statement.span()))?;
+        match flow {
+            Flow::Next => {
+                self.finish_statement(statement);
+            }
+            Flow::Starved(refrigerator) => {
+                self.starved = Some(refrigerator);
+                return Ok(StepResult::NeedsInput(refrigerator));
+            }
+            Flow::Loop(statements) => {
+                self.interpreter.notify(|observer| observer.loop_iterated(recipe.name(), statement, 1));
+                self.frames.last_mut().unwrap().blocks.push(Block {
+                    statements,
+                    position: 0,
+                    looped: Some(statement),
+                    iteration: 1,
+                });
+            }
+            Flow::Call(callee) => {
+                self.mixing_bowls.push_layer();
+                self.baking_dishes.push_layer();
+                self.enter(callee, &HashMap::new());
+            }
+            Flow::Break => {
+                self.interpreter.notify(|observer| observer.statement_finished(recipe.name(), statement));
+                let frame = self.frames.last_mut().unwrap();
+                if frame.blocks.len() == 1 {
+                    return Err(InterpreterError::from("unexpected break").at(recipe.name(), statement.span()));
+                }
+                let block = frame.blocks.pop().unwrap();
+                self.finish_statement(block.looped.unwrap());
+            }
+            Flow::Return => {
+                self.interpreter.notify(|observer| observer.statement_finished(recipe.name(), statement));
+                let frame = self.frames.last_mut().unwrap();
+                while frame.blocks.len() > 1 {
+                    let block = frame.blocks.pop().unwrap();
+                    self.interpreter.notify(|observer| observer.statement_finished(recipe.name(), block.looped.unwrap()));
+                }
+                frame.blocks[0].position = frame.blocks[0].statements.len();
+            }
+            Flow::Exit(exit_code) => {
+                self.interpreter.notify(|observer| observer.statement_finished(recipe.name(), statement));
+                while let Some(frame) = self.frames.pop() {
+                    for block in frame.blocks.iter().skip(1).rev() {
+                        self.interpreter.notify(|observer| observer.statement_finished(frame.recipe.name(), block.looped.unwrap()));
+                    }
+                    self.interpreter.notify(|observer| observer.recipe_exited(frame.recipe.name()));
+                    if let (Some(caller), Some(call)) = (self.frames.last(), self.current_statement()) {
+                        self.interpreter.notify(|observer| observer.statement_finished(caller.recipe.name(), call));
+                    }
+                }
+                self.exit_code = Some(exit_code);
+            }
+        }
+
+        self.settle()?;
+        Ok(if self.is_finished() { StepResult::Finished } else { StepResult::Running })
+    }
+
+    /// Fails if any recipe on the call stack has been cooking for longer than its `Cooking time` allows.
+    fn check_cooking_time(&self) -> InterpreterResult<()> {
+        let cooking_time = self.interpreter.options().cooking_time;
+        for frame in self.frames.iter().rev() {
+            let minutes = match frame.recipe.cooking_time() {
+                Some(minutes) => *minutes,
+                None => continue,
+            };
+            match cooking_time {
+                CookingTime::Ignored => {}
+                CookingTime::Steps(per_minute) => {
+                    let allowed = minutes * per_minute as f64;
+                    if frame.steps as f64 > allowed {
+                        return Err(format!("{} is burnt: it has been cooking for longer than {} minutes ({} statements)", frame.recipe.name(), minutes, allowed).into());
+                    }
+                }
+                CookingTime::Clock(per_minute) => {
+                    let allowed = per_minute.mul_f64(minutes);
+                    if frame.entered.elapsed() > allowed {
+                        return Err(format!("{} is burnt: it has been cooking for longer than {} minutes ({:?})", frame.recipe.name(), minutes, allowed).into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Steps until the recipe has been cooked and returns the exit code it has been [thrown away](Execution::exit_code) with.
+    /// Running into a refrigerator that needs more input is an error.
+    pub fn run(&mut self) -> InterpreterResult<Option<i32>> {
+        loop {
+            match self.step()? {
+                StepResult::Running => {}
+                StepResult::NeedsInput(refrigerator) => return Err(format!("refrigerator {} needs more input", refrigerator).into()),
+                StepResult::Finished => return Ok(self.exit_code),
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The name of the recipe that is currently being cooked.
+    pub fn current_recipe(&self) -> Option<&'a str> {
+        self.frames.last().map(|frame| frame.recipe.name().as_str())
+    }
+
+    /// The statement that the next step executes.
+    pub fn current_statement(&self) -> Option<&'a SpannedStatement> {
+        let block = self.frames.last()?.blocks.last()?;
+        block.statements.get(block.position)
+    }
+
+    /// The refrigerators, e.g. to supply more input after [`StepResult::NeedsInput`].
+    pub fn refrigerators(&mut self) -> &mut Refrigerators {
+        self.refrigerators
+    }
+
+    /// The first mixing bowl of the recipe once it has been cooked.
+    pub fn result(&self) -> Option<&MixingBowl> {
+        self.result.as_ref()
+    }
+
+    /// The exit code that cooking has been ended with by `Throw away`, if it has.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Describes every ingredient of the current recipe and all of its mixing bowls and baking dishes the way `Examine` does.
+    pub fn examine_kitchen(&self) -> String {
+        match self.frames.last() {
+            Some(frame) => examine_kitchen(frame, self.frames.len(), &self.mixing_bowls, &self.baking_dishes),
+            None => String::from("The kitchen is closed."),
+        }
+    }
+
+    fn enter(&mut self, recipe: &'a Recipe, initial_values: &HashMap<String, f64>) {
+        self.interpreter.notify(|observer| observer.recipe_entered(recipe.name()));
+        self.frames.push(Frame {
+            recipe,
+            ingredients: recipe.ingredients().definitions().iter()
+                .map(|def| Ingredient::instantiate(def, initial_values.get(def.name()).copied()))
+                .collect(),
+            blocks: vec![Block {
+                statements: recipe.statements(),
+                position: 0,
+                looped: None,
+                iteration: 1,
+            }],
+            steps: 0,
+            entered: Instant::now(),
+        });
+    }
+
+    fn finish_statement(&mut self, statement: &SpannedStatement) {
+        let frame = self.frames.last_mut().unwrap();
+        self.interpreter.notify(|observer| observer.statement_finished(frame.recipe.name(), statement));
+        frame.blocks.last_mut().unwrap().position += 1;
+    }
+
+    /// Finishes loop iterations and recipes until there is a statement to execute next.
+    fn settle(&mut self) -> InterpreterResult<()> {
+        while let Some(frame) = self.frames.last_mut() {
+            let block = frame.blocks.last_mut().unwrap();
+            if block.position < block.statements.len() {
+                break;
+            }
+            match block.looped {
+                Some(loop_statement) => {
+                    if let Statement::Loop { test_ingredient, decrement_ingredient, .. } = loop_statement.statement() {
+                        if let Some(decrement_ingredient) = decrement_ingredient {
+                            let ingredient = expect_ingredient_mut(&mut frame.ingredients, decrement_ingredient)
+                                .map_err(|err| err.at(frame.recipe.name(), loop_statement.span()))?;
+                            ingredient.value -= 1.0;
+                            self.interpreter.notify(|observer| observer.ingredient_changed(decrement_ingredient, ingredient));
+                        }
+                        let test_ingredient = expect_ingredient(&frame.ingredients, test_ingredient)
+                            .map_err(|err| err.at(frame.recipe.name(), loop_statement.span()))?;
+                        if is_set(test_ingredient) {
+                            block.position = 0;
+                            block.iteration += 1;
+                            let iteration = block.iteration;
+                            self.interpreter.notify(|observer| observer.loop_iterated(frame.recipe.name(), loop_statement, iteration));
+                            continue;
+                        }
+                    }
+                    frame.blocks.pop();
+                    self.finish_statement(loop_statement);
+                }
+                None => self.leave(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Pops the current recipe and hands its first mixing bowl to the caller.
+    fn leave(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        self.interpreter.notify(|observer| observer.recipe_exited(frame.recipe.name()));
+        let result = self.mixing_bowls.get(&1).cloned();
+        if self.frames.is_empty() {
+            self.result = result;
+            return;
+        }
+
+        self.mixing_bowls.pop_layer();
+        self.baking_dishes.pop_layer();
+        if let Some(result_bowl) = result {
+            let target_bowl = self.mixing_bowls.get_mut(1);
+            for ingredient in result_bowl.into_iter() {
+                self.interpreter.notify(|observer| observer.pushed(1, &ingredient));
+                target_bowl.push(ingredient);
+            }
+        }
+        let call = self.current_statement().unwrap();
+        self.finish_statement(call);
+    }
+
+    fn execute(&mut self, statement: &'a Statement) -> InterpreterResult<Flow<'a>> {
+        let interpreter = self.interpreter;
+        let checks = interpreter.options().checks;
+        let bytes = interpreter.options().bytes;
+        let depth = self.frames.len();
+        let frame = self.frames.last_mut().unwrap();
+        let ingredients = &mut frame.ingredients;
+        let mixing_bowls = &mut self.mixing_bowls;
+        match statement {
+            Statement::Read { ingredient: ingredient_name, refrigerator: refrigerator_id } => {
+                let refrigerator = self.refrigerators.get_mut(*refrigerator_id)?;
+                let value = if ingredients.get(ingredient_name).is_some_and(|ingredient| ingredient.liquid) {
+                    refrigerator.read_char()?
+                } else {
+                    refrigerator.read_number()?
+                };
+                let value = match value {
+                    Some(value) => value,
+                    None => return Ok(Flow::Starved(*refrigerator_id)),
+                };
+                let ingredient = ingredients.entry(ingredient_name.clone())
+                    .or_insert(Ingredient { value, liquid: false });
+                ingredient.value = value;
+                interpreter.notify(|observer| observer.input_consumed(*refrigerator_id, ingredient));
+                interpreter.notify(|observer| observer.ingredient_changed(ingredient_name, ingredient));
+            }
+            Statement::CheckInput { ingredient: ingredient_name, refrigerator: refrigerator_id } => {
+                let refrigerator = self.refrigerators.get_mut(*refrigerator_id)?;
+                let ingredient = expect_ingredient_mut(ingredients, ingredient_name)?;
+                let value = if ingredient.liquid {
+                    refrigerator.check_for_anything()?
+                } else {
+                    refrigerator.check_for_number()?
+                };
+                match value {
+                    Some(value) => ingredient.value = value,
+                    None => return Ok(Flow::Starved(*refrigerator_id)),
+                }
+                interpreter.notify(|observer| observer.ingredient_changed(ingredient_name, ingredient));
+            }
+            Statement::Push { ingredient, mixing_bowl } => {
+                let ingredient = expect_ingredient(ingredients, ingredient)?.clone();
+                interpreter.notify(|observer| observer.pushed(*mixing_bowl, &ingredient));
+                mixing_bowls.get_mut(*mixing_bowl).push(ingredient);
+            }
+            Statement::Pop { ingredient: ingredient_name, mixing_bowl: mixing_bowl_id } => {
+                let mixing_bowl = mixing_bowls.get_mut(*mixing_bowl_id);
+                if let Some(value) = mixing_bowl.pop() {
+                    interpreter.notify(|observer| observer.popped(*mixing_bowl_id, &value));
+                    if let Some(ingredient) = ingredients.get_mut(ingredient_name) {
+                        ingredient.value = value.value;
+                    } else {
+                        ingredients.insert(ingredient_name.clone(), value);
+                    }
+                    interpreter.notify(|observer| observer.ingredient_changed(ingredient_name, &ingredients[ingredient_name]));
+                } else {
+                    return Err("tried to pop from empty bowl!".into());
+                }
+            }
+            Statement::Add { ingredient, mixing_bowl } => {
+                expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value +=
+                    expect_ingredient(ingredients, ingredient)?.value;
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::Subtract { ingredient, mixing_bowl } => {
+                expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value -=
+                    expect_ingredient(ingredients, ingredient)?.value;
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::Multiply { ingredient, mixing_bowl } => {
+                expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value *=
+                    expect_ingredient(ingredients, ingredient)?.value;
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::Divide { ingredient, mixing_bowl } => {
+                let top = expect_bowl_top_mut(mixing_bowls, mixing_bowl)?;
+                top.value = divide(top.value, expect_ingredient(ingredients, ingredient)?.value, checks)?;
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::AddAll { mixing_bowl } => {
+                expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value +=
+                    ingredients.values().filter(|i| !i.liquid).map(|i| i.value).sum::<f64>();
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::ToChar { ingredient: ingredient_name } => {
+                let ingredient = expect_ingredient_mut(ingredients, ingredient_name)?;
+                if checks == RuntimeChecks::Strict {
+                    check_liquefiable(ingredient, bytes)
+                        .map_err(|err| format!("cannot liquefy {}: {}", ingredient_name, err))?;
+                }
+                ingredient.liquid = true;
+                interpreter.notify(|observer| observer.ingredient_changed(ingredient_name, ingredient));
+            }
+            Statement::ToCharAll { mixing_bowl } => {
+                for ingredient in mixing_bowls.get_mut(*mixing_bowl) {
+                    if checks == RuntimeChecks::Strict {
+                        check_liquefiable(ingredient, bytes)
+                            .map_err(|err| format!("cannot liquefy the contents of mixing bowl {}: {}", mixing_bowl, err))?;
+                    }
+                    ingredient.liquid = true;
+                }
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl);
+            }
+            Statement::MoveDynamic { mixing_bowl: mixing_bowl_id, ingredient } => {
+                let depth = expect_ingredient(ingredients, ingredient)?.value;
+                let mixing_bowl = mixing_bowls.get_mut(*mixing_bowl_id);
+                if !mixing_bowl.is_empty() {
+                    let position = stir_position(mixing_bowl.len() - 1, depth, checks)?;
+                    let top = mixing_bowl.pop().unwrap();
+                    mixing_bowl.insert(position, top);
+                }
+                notify_bowl_changed(interpreter, mixing_bowls, *mixing_bowl_id);
+            }
+            Statement::MoveStatic { mixin_bowl, offset } => {
+                let mixing_bowl = mixing_bowls.get_mut(*mixin_bowl);
+                if !mixing_bowl.is_empty() {
+                    let position = stir_position(mixing_bowl.len() - 1, *offset as f64, checks)?;
+                    let top = mixing_bowl.pop().unwrap();
+                    mixing_bowl.insert(position, top);
+                }
+                notify_bowl_changed(interpreter, mixing_bowls, *mixin_bowl);
+            }
+            Statement::Sort { mixing_bowl: mixing_bowl_id } => {
+                let mixing_bowl = mixing_bowls.get_mut(*mixing_bowl_id);
+                mixing_bowl.retain(|i| !i.liquid);
+                mixing_bowl.sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal));
+                interpreter.notify(|observer| observer.bowl_sorted(*mixing_bowl_id, mixing_bowl));
+            }
+            Statement::Shuffle { mixing_bowl: mixing_bowl_id } => {
+                let mixing_bowl = mixing_bowls.get_mut(*mixing_bowl_id);
+                let slice = mixing_bowl.as_mut_slice();
+                slice.shuffle(&mut thread_rng());
+                interpreter.notify(|observer| observer.bowl_shuffled(*mixing_bowl_id, mixing_bowl));
+            }
+            Statement::Clear { mixing_bowl } => {
+                mixing_bowls.get_mut(*mixing_bowl).clear();
+                interpreter.notify(|observer| observer.bowl_cleared(*mixing_bowl));
+            }
+            Statement::SetResult { mixing_bowl, baking_dish: baking_dish_id } => {
+                let baking_dish = self.baking_dishes.get_mut(*baking_dish_id);
+                for ingredient in mixing_bowls.get(mixing_bowl)
+                    .ok_or_else(|| format!("no mixing bowl {}", mixing_bowl))?.iter().rev() {
+                    baking_dish.push(ingredient.clone());
+                }
+                interpreter.notify(|observer| observer.dish_poured(*mixing_bowl, *baking_dish_id, baking_dish));
+            }
+            Statement::Examine { ingredient: ingredient_name } => {
+                self.output.debug(&examine::ingredient(ingredient_name, ingredients.get(ingredient_name)))?;
+            }
+            Statement::ExamineBowl { mixing_bowl } => {
+                self.output.debug(&examine::mixing_bowl(*mixing_bowl, mixing_bowls.get(mixing_bowl).map(Vec::as_slice)))?;
+            }
+            Statement::ExamineDish { baking_dish } => {
+                self.output.debug(&examine::baking_dish(*baking_dish, self.baking_dishes.get(baking_dish).map(Vec::as_slice)))?;
+            }
+            Statement::ExamineRefrigerator { refrigerator } => {
+                self.output.debug(&examine::refrigerator(*refrigerator, self.refrigerators.get(*refrigerator).map(|refrigerator| refrigerator.unread())))?;
+            }
+            Statement::ExamineKitchen => {
+                self.output.debug(&examine_kitchen(frame, depth, mixing_bowls, &self.baking_dishes))?;
+            }
+            Statement::Taste { ingredient: ingredient_name } => {
+                let ingredient = expect_ingredient(ingredients, ingredient_name)?;
+                if !is_set(ingredient) {
+                    return Err(format!("taste test failed: {} is {}", ingredient_name, describe(ingredient)).into());
+                }
+            }
+            Statement::TasteBowl { mixing_bowl, ingredient: ingredient_name } => {
+                let expected = expect_ingredient(ingredients, ingredient_name)?;
+                match mixing_bowls.get(mixing_bowl).and_then(|mixing_bowl| mixing_bowl.last()) {
+                    Some(top) if same_value(top.value, expected.value) => {}
+                    Some(top) => return Err(format!("taste test failed: the top of mixing bowl {} is {}, but {} is {}",
+                        mixing_bowl, describe(top), ingredient_name, describe(expected)).into()),
+                    None => return Err(format!("taste test failed: mixing bowl {} is empty, but {} is {}",
+                        mixing_bowl, ingredient_name, describe(expected)).into()),
+                }
+            }
+            Statement::Loop { test_ingredient, statements, .. } => {
+                if is_set(expect_ingredient(ingredients, test_ingredient)?) {
+                    return Ok(Flow::Loop(statements));
+                }
+            }
+            Statement::BreakLoop => {
+                return Ok(Flow::Break);
+            }
+            Statement::CallAuxiliary { recipe } => {
+                if let Some(callee) = interpreter.recipes().get(recipe) {
+                    return Ok(Flow::Call(callee));
+                }
+                let native = match interpreter.native_recipe(recipe) {
+                    Some(native) => native,
+                    None => return interpreter.cookbook().get(recipe)
+                        .map(Flow::Call)
+                        .ok_or_else(|| format!("unknown recipe: {}", recipe).into()),
+                };
+                interpreter.notify(|observer| observer.recipe_entered(recipe));
+                let result = native(
+                    mixing_bowls.visible().into_iter().map(|(id, mixing_bowl)| (*id, mixing_bowl.clone())).collect(),
+                    self.baking_dishes.visible().into_iter().map(|(id, baking_dish)| (*id, baking_dish.clone())).collect(),
+                )?;
+                interpreter.notify(|observer| observer.recipe_exited(recipe));
+                if let Some(result_bowl) = result {
+                    let target_bowl = mixing_bowls.get_mut(1);
+                    for ingredient in result_bowl.into_iter() {
+                        interpreter.notify(|observer| observer.pushed(1, &ingredient));
+                        target_bowl.push(ingredient);
+                    }
+                }
+            }
+            Statement::Return { count } => {
+                if *count > 0 {
+                    for i in 1..=*count {
+                        if let Some(dish) = self.baking_dishes.get(&i) {
+                            self.output.serve(i, dish)?;
+                            interpreter.notify(|observer| observer.output_produced(i, dish));
+                        }
+                    }
+                }
+                return Ok(Flow::Return);
+            }
+            Statement::Exit { ingredient } => {
+                return Ok(Flow::Exit(exit_code(expect_ingredient(ingredients, ingredient)?.value)?));
+            }
+        }
+        Ok(Flow::Next)
+    }
+}
+
+/// Describes every ingredient of a recipe and all mixing bowls and baking dishes it can see,
+/// headed by how deep the recipe has been served.
+fn examine_kitchen(frame: &Frame, depth: usize, mixing_bowls: &MixingBowls, baking_dishes: &BakingDishes) -> String {
+    let mut lines = vec![format!("Kitchen of {} at call depth {}:", frame.recipe.name(), depth)];
+    let mut ingredients: Vec<_> = frame.ingredients.iter().collect();
+    ingredients.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, ingredient) in ingredients {
+        lines.push(examine::ingredient(name, Some(ingredient)));
+    }
+    for (id, mixing_bowl) in mixing_bowls.visible() {
+        lines.push(examine::mixing_bowl(*id, Some(mixing_bowl)));
+    }
+    for (id, baking_dish) in baking_dishes.visible() {
+        lines.push(examine::baking_dish(*id, Some(baking_dish)));
+    }
+    lines.join("\n")
+}
+
+/// Divides, saturating division by zero in lenient mode:
+/// zero divided by zero is zero, anything else becomes the largest finite value with the dividend's sign.
+fn divide(dividend: f64, divisor: f64, checks: RuntimeChecks) -> InterpreterResult<f64> {
+    if divisor != 0.0 {
+        return Ok(dividend / divisor);
+    }
+    match checks {
+        RuntimeChecks::Strict => Err(format!("cannot divide {} by zero", dividend).into()),
+        RuntimeChecks::Lenient if dividend == 0.0 => Ok(0.0),
+        RuntimeChecks::Lenient => Ok(f64::MAX.copysign(dividend)),
+    }
+}
+
+/// Where the top ingredient of a mixing bowl ends up when it is stirred `depth` ingredients deep
+/// into the `len` ingredients below it.
+/// In lenient mode, the depth is truncated and clamped, so stirring too deep puts it at the bottom.
+fn stir_position(len: usize, depth: f64, checks: RuntimeChecks) -> InterpreterResult<usize> {
+    if checks == RuntimeChecks::Strict && !(depth >= 0.0 && depth.fract() == 0.0 && depth <= len as f64) {
+        return Err(format!("cannot stir {} ingredients deep, there are only {} below the top", depth, len).into());
+    }
+    Ok(len - (depth as usize).min(len))
+}
+
+fn check_liquefiable(ingredient: &Ingredient, bytes: bool) -> Result<(), String> {
+    if bytes {
+        if ingredient.value.fract() != 0.0 || !(0.0..=255.0).contains(&ingredient.value) {
+            return Err(format!("{} is not a byte", ingredient.value));
+        }
+    } else if ingredient.checked_char().is_none() {
+        return Err(format!("{} is not a character", ingredient.value));
+    }
+    Ok(())
+}
+
+/// The exit code for a value, truncated towards zero.
+/// Operating systems only keep exit codes from 0 to 255, so other values can't be thrown away with.
+fn exit_code(value: f64) -> InterpreterResult<i32> {
+    if (0.0..256.0).contains(&value) {
+        Ok(value as i32)
+    } else {
+        Err(format!("cannot throw away with status {}, only statuses from 0 to 255 can be passed on", value).into())
+    }
+}
+
+/// An ingredient's value for error messages, marking liquids.
+fn describe(ingredient: &Ingredient) -> String {
+    if ingredient.liquid {
+        format!("{} ({:?})", ingredient.value, ingredient.as_char())
+    } else {
+        ingredient.value.to_string()
+    }
+}
+
+/// Whether a loop goes on with the given ingredient.
+fn is_set(ingredient: &Ingredient) -> bool {
+    !same_value(ingredient.value, 0.0)
+}
+
+/// Whether two values are the same, give or take rounding errors.
+fn same_value(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 0.0000000001
+}
+
+fn notify_bowl_changed(interpreter: &Interpreter, mixing_bowls: &MixingBowls, mixing_bowl: MixingBowlId) {
+    if let Some(contents) = mixing_bowls.get(&mixing_bowl) {
+        interpreter.notify(|observer| observer.bowl_changed(mixing_bowl, contents));
+    }
+}
+
+fn expect_ingredient<'a>(ingredients: &'a Ingredients, ingredient_name: &String) -> InterpreterResult<&'a Ingredient> {
+    ingredients.get(ingredient_name).ok_or_else(|| format!("no such ingredient: {}", ingredient_name).into())
+}
+
+fn expect_ingredient_mut<'a>(ingredients: &'a mut Ingredients, ingredient_name: &String) -> InterpreterResult<&'a mut Ingredient> {
+    ingredients.get_mut(ingredient_name).ok_or_else(|| format!("no such ingredient: {}", ingredient_name).into())
+}
+
+fn expect_bowl_top_mut<'a>(mixing_bowls: &'a mut MixingBowls, mixing_bowl_id: &MixingBowlId) -> InterpreterResult<&'a mut Ingredient> {
+    let mixing_bowl = mixing_bowls.get_mut(*mixing_bowl_id);
+    mixing_bowl.last_mut().ok_or_else(|| format!("no ingredient in mixing bowl {}", mixing_bowl_id).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::recipe::recipes_from;
+    use crate::grammar::parse;
+    use crate::interpreter::interpreter::{Interpreter, InterpreterOptions, RuntimeChecks};
+    use crate::interpreter::output::{MemorySink, Output, OutputFormat};
+    use crate::interpreter::refrigerator::{EofBehavior, Refrigerators};
+    use crate::interpreter::snapshot::{Snapshot, SNAPSHOT_VERSION};
+
+    use super::*;
+
+    fn interpreter(code: &str, checks: RuntimeChecks) -> Interpreter {
+        let recipes = recipes_from(parse(code).unwrap().next().unwrap()).unwrap();
+        Interpreter::with_options(recipes, InterpreterOptions { checks, ..InterpreterOptions::default() })
+    }
+
+    fn output(served: &MemorySink) -> Output {
+        let mut output = Output::new(Box::new(served.clone()), OutputFormat::Spec, false);
+        output.set_debug_sink(None);
+        output
+    }
+
+    /// Cooks the main recipe and returns what has been served.
+    fn cook(code: &str, checks: RuntimeChecks) -> InterpreterResult<String> {
+        let interpreter = interpreter(code, checks);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        interpreter.run_main_with(&mut refrigerators, &mut output(&served))?;
+        Ok(String::from_utf8(served.contents()).unwrap())
+    }
+
+    const NESTED_LOOPS: &str = "Nested loops.
+
+Ingredients.
+2 g rows
+3 g columns
+1 g one
+
+Method.
+Chop the rows.
+Put rows into the mixing bowl.
+Slice the columns.
+Put columns into the mixing bowl.
+Set aside.
+Put one into the mixing bowl.
+Slice until sliced.
+Chop the rows until chopped.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+    #[test]
+    fn set_aside_leaves_the_innermost_loop() {
+        assert_eq!(cook(NESTED_LOOPS, RuntimeChecks::Lenient).unwrap(), "3 1 3 2\n");
+    }
+
+    #[test]
+    fn loops_push_and_pop_blocks() {
+        let interpreter = interpreter(NESTED_LOOPS, RuntimeChecks::Lenient);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        let mut output = output(&served);
+        let mut execution = interpreter.start("nested loops", &mut refrigerators, &mut output).unwrap();
+        let depth = |execution: &Execution| execution.frames.last().unwrap().blocks.len();
+
+        assert_eq!(execution.step().unwrap(), StepResult::Running);
+        assert_eq!(depth(&execution), 2);
+        execution.step().unwrap();
+        execution.step().unwrap();
+        assert_eq!(depth(&execution), 3);
+        execution.step().unwrap();
+        assert!(matches!(execution.current_statement().unwrap().statement(), Statement::BreakLoop));
+        execution.step().unwrap();
+        assert_eq!(depth(&execution), 2);
+        assert!(matches!(execution.current_statement().unwrap().statement(), Statement::Push { ingredient, .. } if ingredient == "rows"));
+        assert_eq!(execution.run().unwrap(), None);
+        assert!(execution.is_finished());
+    }
+
+    const AUXILIARY: &str = "Caller.
+
+Ingredients.
+5 g flour
+
+Method.
+Put flour into the mixing bowl.
+Serve with doubled flour.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Doubled flour.
+
+Ingredients.
+2 g two
+
+Method.
+Fold two into the mixing bowl.
+Put two into the mixing bowl.
+Add two to the mixing bowl.
+Put two into the 2nd mixing bowl.
+";
+
+    #[test]
+    fn auxiliary_recipes_hand_back_their_first_mixing_bowl() {
+        let interpreter = interpreter(AUXILIARY, RuntimeChecks::Lenient);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        let mut output = output(&served);
+        let mut execution = interpreter.start("caller", &mut refrigerators, &mut output).unwrap();
+
+        execution.step().unwrap();
+        execution.step().unwrap();
+        assert_eq!(execution.current_recipe(), Some("doubled flour"));
+        assert_eq!(execution.frames.len(), 2);
+        for _ in 0..4 {
+            execution.step().unwrap();
+        }
+        assert_eq!(execution.current_recipe(), Some("caller"));
+        assert_eq!(execution.mixing_bowls.get(&1).unwrap().iter().map(|i| i.value).collect::<Vec<_>>(), vec![5.0, 10.0]);
+        assert!(execution.mixing_bowls.get(&2).is_none());
+        assert_eq!(execution.run().unwrap(), None);
+        drop(execution);
+        assert_eq!(String::from_utf8(served.contents()).unwrap(), "10 5\n");
+    }
+
+    const REFRIGERATE: &str = "Refrigerating.
+
+Ingredients.
+1 g one
+2 g two
+
+Method.
+Put one into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+Put two into the mixing bowl.
+Pour contents of the mixing bowl into the 2nd baking dish.
+Serve with chilled.
+Refrigerate for 1 hours.
+Put one into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 2.
+
+Chilled.
+
+Ingredients.
+3 g three
+
+Method.
+Chop the three.
+Put three into the mixing bowl.
+Refrigerate.
+Chop until chopped.
+Put three into the mixing bowl.
+";
+
+    #[test]
+    fn refrigerate_returns_from_loops_and_serves_the_given_dishes() {
+        // The auxiliary recipe returns in the first iteration, handing back its bowl with 1 and 2 below its own 3.
+        // The main recipe then serves its first baking dish only and skips everything after it.
+        assert_eq!(cook(REFRIGERATE, RuntimeChecks::Lenient).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn refrigerate_without_hours_serves_nothing() {
+        let code = REFRIGERATE.replace("Refrigerate for 1 hours.", "Refrigerate.");
+        assert_eq!(cook(&code, RuntimeChecks::Lenient).unwrap(), "");
+    }
+
+    const STIR: &str = "Stirring.
+
+Ingredients.
+1 g one
+2 g two
+3 g three
+5 g depth
+
+Method.
+Put one into the mixing bowl.
+Put two into the mixing bowl.
+Put three into the mixing bowl.
+Stir depth into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+    #[test]
+    fn stirring_past_the_bottom_is_clamped_when_lenient() {
+        assert_eq!(cook(STIR, RuntimeChecks::Lenient).unwrap(), "2 1 3\n");
+        let code = STIR.replace("Stir depth into the mixing bowl.", "Stir the mixing bowl for 7 minutes.");
+        assert_eq!(cook(&code, RuntimeChecks::Lenient).unwrap(), "2 1 3\n");
+    }
+
+    #[test]
+    fn stirring_past_the_bottom_fails_when_strict() {
+        let err = cook(STIR, RuntimeChecks::Strict).unwrap_err();
+        assert_eq!(err.message, "cannot stir 5 ingredients deep, there are only 2 below the top");
+        assert_eq!(err.location.unwrap().span.begin, (13, 1));
+        let code = STIR.replace("5 g depth", "2 g depth");
+        assert_eq!(cook(&code, RuntimeChecks::Strict).unwrap(), "2 1 3\n");
+    }
+
+    #[test]
+    fn dividing_by_zero_saturates_when_lenient() {
+        assert_eq!(divide(6.0, 3.0, RuntimeChecks::Strict).unwrap(), 2.0);
+        assert_eq!(divide(0.0, 0.0, RuntimeChecks::Lenient).unwrap(), 0.0);
+        assert_eq!(divide(5.0, 0.0, RuntimeChecks::Lenient).unwrap(), f64::MAX);
+        assert_eq!(divide(-5.0, 0.0, RuntimeChecks::Lenient).unwrap(), f64::MIN);
+        assert!(divide(5.0, 0.0, RuntimeChecks::Strict).is_err());
+    }
+
+    #[test]
+    fn throw_away_ends_all_recipes() {
+        let code = "Thrower.
+
+Ingredients.
+1 g one
+
+Method.
+Serve with discarded.
+Put one into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Discarded.
+
+Ingredients.
+3 g status
+
+Method.
+Chop the status.
+Throw away the status.
+Chop until chopped.
+";
+        let interpreter = interpreter(code, RuntimeChecks::Lenient);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        assert_eq!(interpreter.run_main_with(&mut refrigerators, &mut output(&served)).unwrap(), Some(3));
+        assert!(served.contents().is_empty());
+    }
+
+    #[test]
+    fn throw_away_only_passes_on_statuses_of_a_byte() {
+        // The status is one less than the ingredient, so that it can go below zero.
+        let throw = |status: &str| {
+            let code = format!(
+                "Thrower.\n\nIngredients.\n{} g status\n1 g one\n\nMethod.\nPut status into the mixing bowl.\nRemove one from the mixing bowl.\nFold status into the mixing bowl.\nThrow away the status.\n",
+                status,
+            );
+            let interpreter = interpreter(&code, RuntimeChecks::Lenient);
+            let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+            interpreter.run_main_with(&mut refrigerators, &mut output(&MemorySink::default()))
+        };
+        assert_eq!(throw("1").unwrap(), Some(0));
+        assert_eq!(throw("256.5").unwrap(), Some(255));
+        assert_eq!(throw("257").unwrap_err().to_string(), "11:1 in thrower: cannot throw away with status 256, only statuses from 0 to 255 can be passed on");
+        assert_eq!(throw("0").unwrap_err().to_string(), "11:1 in thrower: cannot throw away with status -1, only statuses from 0 to 255 can be passed on");
+    }
+
+    const TAKING: &str = "Taking.
+
+Ingredients.
+0 g first
+0 g second
+
+Method.
+Take first from refrigerator.
+Take second from refrigerator.
+Put first into the mixing bowl.
+Put second into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+    /// Steps until cooking is done or the refrigerator runs dry.
+    fn step_until_stuck(execution: &mut Execution) -> StepResult {
+        loop {
+            match execution.step().unwrap() {
+                StepResult::Running => {}
+                result => return result,
+            }
+        }
+    }
+
+    #[test]
+    fn refrigerators_stocked_by_hand_pause_cooking() {
+        let interpreter = interpreter(TAKING, RuntimeChecks::Lenient);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        refrigerators.stock_by_hand(1);
+        let served = MemorySink::default();
+        let mut output = output(&served);
+        let mut execution = interpreter.start("taking", &mut refrigerators, &mut output).unwrap();
+
+        assert_eq!(step_until_stuck(&mut execution), StepResult::NeedsInput(1));
+        execution.refrigerators().get_mut(1).unwrap().supply(b"3\n");
+        assert_eq!(step_until_stuck(&mut execution), StepResult::NeedsInput(1));
+        assert!(matches!(execution.current_statement().unwrap().statement(), Statement::Read { ingredient, .. } if ingredient == "second"));
+        // A line only counts once its newline has been supplied, or the refrigerator has been closed.
+        execution.refrigerators().get_mut(1).unwrap().supply(b"4");
+        assert_eq!(step_until_stuck(&mut execution), StepResult::NeedsInput(1));
+        execution.refrigerators().get_mut(1).unwrap().close();
+        assert_eq!(step_until_stuck(&mut execution), StepResult::Finished);
+        drop(execution);
+        output.finish().unwrap();
+        assert_eq!(String::from_utf8(served.contents()).unwrap(), "4 3\n");
+    }
+
+    fn interpreter_with_eof(code: &str, eof_behavior: EofBehavior) -> Interpreter {
+        let recipes = recipes_from(parse(code).unwrap().next().unwrap()).unwrap();
+        Interpreter::with_options(recipes, InterpreterOptions { eof_behavior, ..InterpreterOptions::default() })
+    }
+
+    #[test]
+    fn closed_refrigerators_hand_out_the_end_of_input() {
+        let interpreter = interpreter_with_eof(TAKING, EofBehavior::Zero);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        refrigerators.stock_by_hand(1);
+        let served = MemorySink::default();
+        let mut output = output(&served);
+        let mut execution = interpreter.start("taking", &mut refrigerators, &mut output).unwrap();
+        execution.refrigerators().get_mut(1).unwrap().supply(b"5\n");
+        execution.refrigerators().get_mut(1).unwrap().close();
+        assert_eq!(step_until_stuck(&mut execution), StepResult::Finished);
+        drop(execution);
+        output.finish().unwrap();
+        assert_eq!(String::from_utf8(served.contents()).unwrap(), "0 5\n");
+    }
+
+    #[test]
+    fn closed_refrigerators_fail_at_the_end_of_input() {
+        let interpreter = interpreter_with_eof(TAKING, EofBehavior::Error);
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        refrigerators.stock_by_hand(1);
+        let mut output = output(&MemorySink::default());
+        let mut execution = interpreter.start("taking", &mut refrigerators, &mut output).unwrap();
+        assert_eq!(step_until_stuck(&mut execution), StepResult::NeedsInput(1));
+        execution.refrigerators().get_mut(1).unwrap().close();
+        let err = execution.step().unwrap_err();
+        assert_eq!(err.to_string(), "8:1 in taking: the refrigerator is empty");
+    }
+
+    const LOOPED_CALLS: &str = "Looper.
+
+Ingredients.
+2 g rounds
+
+Method.
+Chop the rounds.
+Put rounds into the mixing bowl.
+Serve with counter.
+Chop the rounds until chopped.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Counter.
+
+Ingredients.
+2 g count
+
+Method.
+Mash the count.
+Add count to the mixing bowl.
+Mash the count until mashed.
+";
+
+    /// Steps until the auxiliary recipe starts the second iteration of its loop and takes a snapshot there.
+    fn snapshot_inside_the_auxiliary_loop(interpreter: &Interpreter) -> Snapshot {
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let mut output = output(&MemorySink::default());
+        let mut execution = interpreter.start("looper", &mut refrigerators, &mut output).unwrap();
+        while !(execution.frames.len() == 2 && execution.frames[1].blocks.len() == 2 && execution.frames[1].blocks[1].iteration == 2) {
+            assert_eq!(execution.step().unwrap(), StepResult::Running);
+        }
+        execution.snapshot()
+    }
+
+    #[test]
+    fn snapshots_resume_where_they_have_been_taken() {
+        let interpreter = interpreter(LOOPED_CALLS, RuntimeChecks::Lenient);
+        let uninterrupted = cook(LOOPED_CALLS, RuntimeChecks::Lenient).unwrap();
+
+        let snapshot = snapshot_inside_the_auxiliary_loop(&interpreter);
+        assert_eq!(snapshot.frames.iter().map(|frame| frame.positions.clone()).collect::<Vec<_>>(), vec![vec![0, 1], vec![0, 0]]);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: Snapshot = serde_json::from_str(&json).unwrap();
+
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        let mut output = output(&served);
+        let mut execution = interpreter.resume(&snapshot, &mut refrigerators, &mut output).unwrap();
+        assert_eq!(execution.run().unwrap(), None);
+        drop(execution);
+        output.finish().unwrap();
+        assert_eq!(String::from_utf8(served.contents()).unwrap(), uninterrupted);
+    }
+
+    #[test]
+    fn snapshots_of_other_versions_are_rejected() {
+        let interpreter = interpreter(LOOPED_CALLS, RuntimeChecks::Lenient);
+        let mut snapshot = snapshot_inside_the_auxiliary_loop(&interpreter);
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let mut output = output(&MemorySink::default());
+        let err = interpreter.resume(&snapshot, &mut refrigerators, &mut output).err().unwrap();
+        assert_eq!(err.to_string(), format!("snapshot has version {}, expected {}", SNAPSHOT_VERSION + 1, SNAPSHOT_VERSION));
+    }
+
+    #[test]
+    fn snapshots_with_positions_that_do_not_exist_are_rejected() {
+        let interpreter = interpreter(LOOPED_CALLS, RuntimeChecks::Lenient);
+        let resume = |snapshot: &Snapshot| {
+            let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+            let mut output = output(&MemorySink::default());
+            interpreter.resume(snapshot, &mut refrigerators, &mut output).err().unwrap().to_string()
+        };
+
+        let mut snapshot = snapshot_inside_the_auxiliary_loop(&interpreter);
+        snapshot.frames[1].positions = vec![0, 7];
+        assert_eq!(resume(&snapshot), "snapshot position 7 is out of bounds in recipe counter");
+
+        let mut snapshot = snapshot_inside_the_auxiliary_loop(&interpreter);
+        snapshot.frames[1].positions = vec![1, 0];
+        assert_eq!(resume(&snapshot), "snapshot position 1 in recipe counter is not a loop");
+
+        let mut snapshot = snapshot_inside_the_auxiliary_loop(&interpreter);
+        snapshot.frames[0].positions = vec![0, 0];
+        assert_eq!(resume(&snapshot), "snapshot position 0 in recipe looper does not serve counter");
+    }
+
+    const NATIVE_CALL: &str = "Native caller.
+
+Ingredients.
+4 g side
+
+Method.
+Put side into the mixing bowl.
+Serve with Square.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+    #[test]
+    fn native_recipes_hand_back_their_bowl() {
+        let mut interpreter = interpreter(NATIVE_CALL, RuntimeChecks::Lenient);
+        interpreter.add_native_recipe("Square", |mixing_bowls, _| {
+            let side = mixing_bowls.get(&1).and_then(|bowl| bowl.last()).ok_or("nothing to square")?.value;
+            Ok(Some(vec![Ingredient { value: side * side, liquid: false }]))
+        });
+        assert!(interpreter.native_recipe("Square").is_some());
+        assert!(interpreter.native_recipe(" square ").is_some());
+
+        let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+        let served = MemorySink::default();
+        interpreter.run_main_with(&mut refrigerators, &mut output(&served)).unwrap();
+        // The caller keeps the side it passed, with the square on top.
+        assert_eq!(String::from_utf8(served.contents()).unwrap(), "16 4\n");
+    }
+}
@@ -1,25 +1,46 @@
 use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
 use crate::ast::ingredient::IngredientDefinition;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Ingredient {
     pub value: f64,
     pub liquid: bool,
 }
 
 impl Ingredient {
-    pub fn instantiate(def: &IngredientDefinition) -> (String, Ingredient) {
+    /// Creates an ingredient from its definition.
+    /// An `initial_value` replaces the one from the definition.
+    pub fn instantiate(def: &IngredientDefinition, initial_value: Option<f64>) -> (String, Ingredient) {
         (def.name().clone(), Ingredient {
-            value: def.initial_value().unwrap_or(1.0),
+            value: initial_value.or_else(|| def.initial_value()).unwrap_or(1.0),
             liquid: def.liquid(),
         })
     }
+
+    /// The character with this ingredient's value as its code, if there is one.
+    pub fn checked_char(&self) -> Option<char> {
+        if self.value.fract() != 0.0 || self.value < 0.0 || self.value > char::MAX as u32 as f64 {
+            return None;
+        }
+        char::from_u32(self.value as u32)
+    }
+
+    /// The character with this ingredient's value as its code.
+    /// The value is truncated and clamped to the range of Unicode code points,
+    /// surrogates become the replacement character.
+    pub fn as_char(&self) -> char {
+        let code = (self.value as u32).min(char::MAX as u32);
+        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
 }
 
 impl Display for Ingredient {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if self.liquid {
-            write!(f, "{}", char::from_u32(self.value as u32).map_or_else(|| "<missingno>".to_string(), |c| c.to_string()))
+            write!(f, "{}", self.as_char())
         } else {
             write!(f, "{}", self.value)
         }
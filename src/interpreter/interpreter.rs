@@ -1,253 +1,321 @@
-use std::cmp::{max, Ordering};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
 
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use getset::{CopyGetters, Getters};
 
-use crate::ast::recipe::Recipes;
-use crate::ast::statement::Statement;
-use crate::ast::util::{BakingDishId, MixingBowlId};
+use crate::ast::recipe::{Recipe, Recipes};
+use crate::ast::util::{BakingDishId, MixingBowlId, Span};
+use crate::cookbook;
+use crate::interpreter::backtrace::Backtrace;
+use crate::interpreter::execution::Execution;
 use crate::interpreter::ingredient::Ingredient;
-use crate::interpreter::util::{check_for_number, LazyTreeMap, LazyTreeMapParent, read_char, read_number};
+use crate::interpreter::kitchen::{normalized_ingredients, KitchenSetup};
+use crate::interpreter::number::JunkPolicy;
+use crate::interpreter::observer::InterpreterObserver;
+use crate::interpreter::output::{Output, OutputFormat};
+use crate::interpreter::refrigerator::{EofBehavior, RefrigeratorSettings, Refrigerators};
+use crate::interpreter::snapshot::Snapshot;
+use crate::interpreter::util::LayeredMap;
 
 pub type MixingBowl = Vec<Ingredient>;
 pub type BakingDish = Vec<Ingredient>;
-pub type MixingBowls<'a> = LazyTreeMap<'a, MixingBowlId, MixingBowl>;
-pub type MixingBowlsParent<'a> = LazyTreeMapParent<'a, MixingBowlId, MixingBowl>;
-pub type BakingDishes<'a> = LazyTreeMap<'a, BakingDishId, BakingDish>;
-pub type BakingDishesParent<'a> = LazyTreeMapParent<'a, BakingDishId, BakingDish>;
+pub type MixingBowls = LayeredMap<MixingBowlId, MixingBowl>;
+pub type BakingDishes = LayeredMap<BakingDishId, BakingDish>;
 pub type Ingredients = HashMap<String, Ingredient>;
 
 pub type InterpreterResult<T> = Result<T, InterpreterError>;
-pub type InterpreterError = String;
 
-pub struct Interpreter {
-    recipes: Recipes,
+/// An auxiliary recipe written in Rust.
+/// It gets copies of the mixing bowls and baking dishes of the recipe serving it and returns the mixing bowl
+/// whose contents are put on top of the caller's first mixing bowl, like a Chef recipe hands back its first mixing bowl.
+pub type NativeRecipe = dyn Fn(BTreeMap<MixingBowlId, MixingBowl>, BTreeMap<BakingDishId, BakingDish>) -> InterpreterResult<Option<MixingBowl>>;
+
+/// Something that went wrong while cooking.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterpreterError {
+    pub message: String,
+    /// Where it went wrong, if it happened while executing a statement.
+    pub location: Option<ErrorLocation>,
+    /// The state of the kitchen when it went wrong, if it happened while cooking.
+    pub backtrace: Option<Box<Backtrace>>,
 }
 
-impl Interpreter {
-    pub fn new(recipes: Recipes) -> Interpreter {
-        Interpreter { recipes }
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorLocation {
+    pub recipe: String,
+    pub span: Span,
+}
+
+impl InterpreterError {
+    pub fn new(message: String) -> InterpreterError {
+        InterpreterError {
+            message,
+            location: None,
+            backtrace: None,
+        }
     }
 
-    pub fn run_main(&self) -> InterpreterResult<()> {
-        self.run_recipe(self.recipes.front().unwrap().0.clone(), MixingBowlsParent::None, BakingDishesParent::None, &mut String::new())
-            .map(|_| ())
+    /// Locates the error at the given statement, unless it has been located already.
+    pub fn at(mut self, recipe: &str, span: Span) -> InterpreterError {
+        if self.location.is_none() {
+            self.location = Some(ErrorLocation {
+                recipe: recipe.to_string(),
+                span,
+            });
+        }
+        self
     }
+}
 
-    pub fn run_recipe(&self, recipe_name: String, parent_bowls: MixingBowlsParent, parent_dishes: BakingDishesParent, read_buffer: &mut String) -> InterpreterResult<Option<MixingBowl>> {
-        let recipe = self.recipes.get(&*recipe_name);
-        if let None = recipe {
-            return Err(format!("unknown recipe: {}", recipe_name));
+impl From<String> for InterpreterError {
+    fn from(message: String) -> Self {
+        InterpreterError::new(message)
+    }
+}
+
+impl From<&str> for InterpreterError {
+    fn from(message: &str) -> Self {
+        InterpreterError::new(message.to_string())
+    }
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} in {}: {}", location.span, location.recipe, self.message),
+            None => write!(f, "{}", self.message),
         }
-        let recipe = recipe.unwrap();
+    }
+}
 
-        let mut mixing_bowls = MixingBowls::new(parent_bowls, |_| MixingBowl::new());
-        let mut baking_dishes = BakingDishes::new(parent_dishes, |_| BakingDish::new());
-        let mut ingredients: Ingredients = recipe.ingredients().definitions().iter().map(Ingredient::instantiate).collect();
+/// How operations whose result the specification leaves open are handled.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RuntimeChecks {
+    /// They are runtime errors.
+    Strict,
+    /// They saturate to the closest sensible result.
+    #[default]
+    Lenient,
+}
 
-        match self.execute_statements(recipe.statements(), &mut mixing_bowls, &mut baking_dishes, &mut ingredients, read_buffer)? {
-            ExecutionCode::Break => { return Err("unexpected break".to_string()) }
-            _ => {}
+impl FromStr for RuntimeChecks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "strict" => Ok(RuntimeChecks::Strict),
+            "lenient" => Ok(RuntimeChecks::Lenient),
+            _ => Err(format!("unknown runtime checks: {} (expected strict or lenient)", s)),
         }
+    }
+}
 
-        Ok(mixing_bowls.get(&1).map(|bowl| bowl.to_owned()))
-    }
-
-    fn execute_statements<'a>(&self, statements: &Vec<Statement>, mixing_bowls: &'a mut MixingBowls, baking_dishes: &mut BakingDishes, ingredients: &mut Ingredients, read_buffer: &mut String) -> InterpreterResult<ExecutionCode> {
-        for statement in statements {
-            match statement {
-                Statement::Read { ingredient: ingredient_name } => {
-                    if let Some(ingredient) = ingredients.get_mut(ingredient_name) {
-                        if ingredient.liquid {
-                            ingredient.value = read_char(read_buffer) as f64;
-                        } else {
-                            ingredient.value = read_number(read_buffer);
-                        }
-                    } else {
-                        ingredients.insert(ingredient_name.clone(), Ingredient {
-                            value: read_number(read_buffer),
-                            liquid: false,
-                        });
-                    }
-                }
-                Statement::CheckInput { ingredient } => {
-                    let ingredient = Self::expect_ingredient_mut(ingredients, ingredient)?;
-                    if ingredient.liquid {
-                        ingredient.value = if read_buffer.is_empty() { 0.0 } else { 1.0 };
-                    } else {
-                        ingredient.value = if check_for_number(read_buffer) { 1.0 } else { 0.0 };
-                    }
-                }
-                Statement::Push { ingredient, mixing_bowl } => {
-                    mixing_bowls.get_mut(mixing_bowl.clone()).push(
-                        Self::expect_ingredient(&ingredients, ingredient)?.clone()
-                    );
-                }
-                Statement::Pop { ingredient: ingredient_name, mixing_bowl } => {
-                    let mixing_bowl = mixing_bowls.get_mut(mixing_bowl.clone());
-                    if let Some(value) = mixing_bowl.pop() {
-                        if let Some(ingredient) = ingredients.get_mut(ingredient_name) {
-                            ingredient.value = value.value;
-                        } else {
-                            ingredients.insert(ingredient_name.clone(), value);
-                        }
-                    } else {
-                        return Err("tried to pop from empty bowl!".to_string());
-                    }
-                }
-                Statement::Add { ingredient, mixing_bowl } => {
-                    Self::expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value +=
-                        Self::expect_ingredient(&ingredients, ingredient)?.value;
-                }
-                Statement::Subtract { ingredient, mixing_bowl } => {
-                    Self::expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value -=
-                        Self::expect_ingredient(&ingredients, ingredient)?.value;
-                }
-                Statement::Multiply { ingredient, mixing_bowl } => {
-                    Self::expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value *=
-                        Self::expect_ingredient(&ingredients, ingredient)?.value;
-                }
-                Statement::Divide { ingredient, mixing_bowl } => {
-                    Self::expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value /=
-                        Self::expect_ingredient(&ingredients, ingredient)?.value;
-                }
-                Statement::AddAll { mixing_bowl } => {
-                    Self::expect_bowl_top_mut(mixing_bowls, mixing_bowl)?.value +=
-                        ingredients.values().filter(|i| !i.liquid).map(|i| i.value).sum::<f64>();
-                }
-                Statement::ToChar { ingredient } => {
-                    Self::expect_ingredient_mut(ingredients, ingredient)?.liquid = true;
-                }
-                Statement::ToCharAll { mixing_bowl } => {
-                    let mixing_bowl = mixing_bowls.get_mut(mixing_bowl.clone());
-                    for ingredient in mixing_bowl {
-                        ingredient.liquid = true;
-                    }
-                }
-                Statement::MoveDynamic { mixing_bowl, ingredient } => {
-                    let amount = Self::expect_ingredient(&ingredients, ingredient)?.value as usize;
-                    let mixing_bowl = mixing_bowls.get_mut(mixing_bowl.clone());
-                    if let Some(top) = mixing_bowl.pop() {
-                        mixing_bowl.insert(mixing_bowl.len() - amount, top);
-                    }
-                }
-                Statement::MoveStatic { mixin_bowl, offset } => {
-                    let mixing_bowl = mixing_bowls.get_mut(mixin_bowl.clone());
-                    if let Some(top) = mixing_bowl.pop() {
-                        mixing_bowl.insert(max(mixing_bowl.len() as u64 - *offset, 0) as usize, top);
-                    }
-                }
-                Statement::Sort { mixing_bowl } => {
-                    let mixing_bowl = mixing_bowls.get_mut(mixing_bowl.clone());
-                    mixing_bowl.retain(|i| !i.liquid);
-                    mixing_bowl.sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal));
-                }
-                Statement::Shuffle { mixing_bowl } => {
-                    let mixing_bowl = mixing_bowls.get_mut(mixing_bowl.clone());
-                    let slice = mixing_bowl.as_mut_slice();
-                    slice.shuffle(&mut thread_rng());
-                }
-                Statement::Clear { mixing_bowl } => {
-                    mixing_bowls.get_mut(mixing_bowl.clone()).clear();
-                }
-                Statement::SetResult { mixing_bowl, baking_dish } => {
-                    let baking_dish = baking_dishes.get_mut(baking_dish.clone());
-                    for ingredient in mixing_bowls.get(mixing_bowl)
-                        .ok_or_else(|| format!("no mixing bowl {}", mixing_bowl))?.iter().rev() {
-                        baking_dish.push(ingredient.clone());
-                    }
-                }
-                Statement::Examine { ingredient: ingredient_name } => {
-                    if let Some(ingredient) = ingredients.get(ingredient_name) {
-                        println!("There is {} of {}{}", ingredient.value, ingredient_name, if ingredient.liquid { " (liquid)" } else { "" });
-                    } else {
-                        println!("{} does not exist.", ingredient_name);
-                    }
-                }
-                Statement::ExamineBowl { mixing_bowl: mixing_bowl_no } => {
-                    if let Some(mixing_bowl) = mixing_bowls.get(mixing_bowl_no) {
-                        if mixing_bowl.is_empty() {
-                            println!("Mixing bowl {} is empty.", mixing_bowl_no);
-                        } else {
-                            println!("Content of mixing bowl {}:{}", *mixing_bowl_no, mixing_bowl.iter()
-                                .map(|val| format!("\n    {} (liquid: {})", val.value, val.liquid))
-                                .fold(String::new(), |mut a, b| {
-                                    a.push_str(&*b);
-                                    a
-                                })
-                            );
-                        }
-                    } else {
-                        println!("Mixing bowl {} does not exist.", *mixing_bowl_no);
-                    }
-                }
-                Statement::Loop { test_ingredient, decrement_ingredient, statements: loop_statements } => {
-                    while (Self::expect_ingredient(ingredients, test_ingredient)?.value - 0.0).abs() > 0.0000000001 {
-                        match self.execute_statements(loop_statements, mixing_bowls, baking_dishes, ingredients, read_buffer)? {
-                            ExecutionCode::Normal => {},
-                            ExecutionCode::Break => { break },
-                            ExecutionCode::Return => { return Ok(ExecutionCode::Return) }
-                        }
-                        if let Some(decrement_ingredient) = decrement_ingredient {
-                            Self::expect_ingredient_mut(ingredients, decrement_ingredient)?.value -= 1.0;
-                        }
-                    }
-                }
-                Statement::BreakLoop => {
-                    return Ok(ExecutionCode::Break);
-                }
-                Statement::CallAuxiliary { recipe } => {
-                    if let Some(result_bowl) = self.run_recipe(recipe.clone(), MixingBowlsParent::Other(&mixing_bowls), MixingBowlsParent::Other(&baking_dishes), read_buffer)? {
-                        let target_bowl = mixing_bowls.get_mut(1);
-                        for ingredient in result_bowl.into_iter() {
-                            target_bowl.push(ingredient);
-                        }
-                    }
-                }
-                Statement::Return { count } => {
-                    if *count > 0 {
-                        for i in 1..=*count {
-                            if let Some(dish) = baking_dishes.get(&i) {
-                                if dish.iter().any(|i| i.liquid) {
-                                    for ingredient in dish {
-                                        print!("{}", ingredient);
-                                    }
-                                } else {
-                                    for ingredient in dish {
-                                        print!("{}, ", ingredient);
-                                    }
-                                }
-                                print!("\n");
-                            }
-                        }
-                    }
-                    return Ok(ExecutionCode::Return);
-                }
-                _ => {
-                    return Err(format!("unknown statement: {:?}", statement));
-                }
-            }
+impl Display for RuntimeChecks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeChecks::Strict => write!(f, "strict"),
+            RuntimeChecks::Lenient => write!(f, "lenient"),
         }
-        Ok(ExecutionCode::Normal)
     }
+}
+
+/// What the `Cooking time` of a recipe means.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CookingTime {
+    /// It is just part of the recipe's prose.
+    #[default]
+    Ignored,
+    /// Every minute allows the given number of statements, counting those of the auxiliary recipes served meanwhile.
+    Steps(u64),
+    /// Every minute allows the given wall-clock time.
+    Clock(Duration),
+}
+
+impl CookingTime {
+    /// The default number of statements per minute.
+    pub const STEPS_PER_MINUTE: u64 = 1000;
+}
+
+impl FromStr for CookingTime {
+    type Err = String;
 
-    fn expect_ingredient<'a>(ingredients: &'a Ingredients, ingredient_name: &String) -> InterpreterResult<&'a Ingredient> {
-        ingredients.get(ingredient_name).ok_or_else(|| format!("no such ingredient: {}", ingredient_name))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mode, amount) = match s.split_once('=') {
+            Some((mode, amount)) => (mode, Some(amount)),
+            None => (s, None),
+        };
+        match (mode.to_lowercase().as_str(), amount) {
+            ("ignored", None) => Ok(CookingTime::Ignored),
+            ("steps", None) => Ok(CookingTime::Steps(CookingTime::STEPS_PER_MINUTE)),
+            ("steps", Some(amount)) => amount.parse()
+                .map(CookingTime::Steps)
+                .map_err(|err| format!("invalid number of statements per minute {}: {}", amount, err)),
+            ("clock", None) => Ok(CookingTime::Clock(Duration::from_secs(60))),
+            ("clock", Some(amount)) => amount.parse()
+                .map_err(|err| format!("invalid number of seconds per minute {}: {}", amount, err))
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds)
+                    .map_err(|err| format!("invalid number of seconds per minute {}: {}", amount, err)))
+                .map(CookingTime::Clock),
+            _ => Err(format!("unknown cooking time: {} (expected ignored, steps[=<statements per minute>] or clock[=<seconds per minute>])", s)),
+        }
     }
+}
 
-    fn expect_ingredient_mut<'a>(ingredients: &'a mut Ingredients, ingredient_name: &String) -> InterpreterResult<&'a mut Ingredient> {
-        ingredients.get_mut(ingredient_name).ok_or_else(|| format!("no such ingredient: {}", ingredient_name))
+impl Display for CookingTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CookingTime::Ignored => write!(f, "ignored"),
+            CookingTime::Steps(steps) => write!(f, "steps={}", steps),
+            CookingTime::Clock(duration) => write!(f, "clock={}", duration.as_secs_f64()),
+        }
     }
+}
 
-    fn expect_bowl_top_mut<'a>(mixing_bowls: &'a mut MixingBowls, mixing_bowl_id: &MixingBowlId) -> InterpreterResult<&'a mut Ingredient> {
-        let mixing_bowl = mixing_bowls.get_mut(mixing_bowl_id.clone());
-        mixing_bowl.last_mut().ok_or_else(|| format!("no ingredient in mixing bowl {}", mixing_bowl_id))
+#[derive(Clone, Debug, Default)]
+pub struct InterpreterOptions {
+    /// What `Take` and `Check` do once the refrigerator runs empty.
+    pub eof_behavior: EofBehavior,
+    /// What `Take` does with characters in front of a number.
+    pub junk_policy: JunkPolicy,
+    /// How served dishes are written.
+    pub output_format: OutputFormat,
+    /// Liquid ingredients are taken and served as raw bytes instead of Unicode characters.
+    pub bytes: bool,
+    /// The state of the kitchen before the main recipe starts.
+    pub kitchen: KitchenSetup,
+    /// What dividing by zero, liquefying values that aren't characters and stirring too deep do.
+    pub checks: RuntimeChecks,
+    /// Whether recipes that take longer than their `Cooking time` burn.
+    pub cooking_time: CookingTime,
+}
+
+impl InterpreterOptions {
+    pub fn refrigerator_settings(&self) -> RefrigeratorSettings {
+        RefrigeratorSettings {
+            eof_behavior: self.eof_behavior,
+            junk_policy: self.junk_policy,
+            bytes: self.bytes,
+        }
     }
 }
 
-enum ExecutionCode {
-    Normal,
-    Return,
-    Break,
+#[derive(CopyGetters, Getters)]
+pub struct Interpreter {
+    #[getset(get="pub")]
+    recipes: Recipes,
+    #[getset(get="pub")]
+    options: InterpreterOptions,
+    /// The bundled recipes that `Serve with` falls back to.
+    #[getset(get_copy="pub")]
+    cookbook: &'static Recipes,
+    observers: Vec<Rc<dyn InterpreterObserver>>,
+    native_recipes: HashMap<String, Box<NativeRecipe>>,
+}
+
+impl Interpreter {
+    pub fn new(recipes: Recipes) -> Interpreter {
+        Interpreter::with_options(recipes, InterpreterOptions::default())
+    }
+
+    pub fn with_options(recipes: Recipes, options: InterpreterOptions) -> Interpreter {
+        Interpreter { recipes, options, cookbook: cookbook::recipes(), observers: Vec::new(), native_recipes: HashMap::new() }
+    }
+
+    /// Registers an observer that gets told about everything that happens while cooking.
+    pub fn add_observer(&mut self, observer: Rc<dyn InterpreterObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Registers an auxiliary recipe written in Rust that `Serve with` can call by its name.
+    /// Recipe names are matched case-insensitively, and Chef recipes of the same name take precedence.
+    pub fn add_native_recipe(&mut self, name: &str, recipe: impl Fn(BTreeMap<MixingBowlId, MixingBowl>, BTreeMap<BakingDishId, BakingDish>) -> InterpreterResult<Option<MixingBowl>> + 'static) {
+        self.native_recipes.insert(name.trim().to_lowercase(), Box::new(recipe));
+    }
+
+    /// Looks up a Chef recipe, falling back to the cookbook.
+    pub fn recipe(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.get(name).or_else(|| self.cookbook.get(name))
+    }
+
+    /// Looks up a native recipe, case-insensitively like [`add_native_recipe`](Interpreter::add_native_recipe) registers it.
+    pub fn native_recipe(&self, name: &str) -> Option<&NativeRecipe> {
+        self.native_recipes.get(&name.trim().to_lowercase()).map(Box::as_ref)
+    }
+
+    /// Runs the main recipe and returns the exit code it has been thrown away with, if it has.
+    pub fn run_main(&self) -> InterpreterResult<Option<i32>> {
+        let mut refrigerators = Refrigerators::stdin(self.options.refrigerator_settings());
+        let mut output = Output::stdout(self.options.output_format, self.options.bytes);
+        self.run_main_with(&mut refrigerators, &mut output)
+    }
+
+    /// Runs the main recipe, reading from and serving to the given places.
+    pub fn run_main_with(&self, refrigerators: &mut Refrigerators, output: &mut Output) -> InterpreterResult<Option<i32>> {
+        self.run_entry_with(self.main_recipe()?, refrigerators, output)
+    }
+
+    /// The name of the first recipe, which is the main recipe unless another one is chosen.
+    pub fn main_recipe(&self) -> InterpreterResult<&str> {
+        self.recipes.front()
+            .map(|(name, _)| name.as_str())
+            .ok_or_else(|| "there are no recipes to cook".into())
+    }
+
+    /// Runs the recipe with the given name as if it were the main recipe.
+    pub fn run_entry(&self, entry: &str) -> InterpreterResult<Option<i32>> {
+        let mut refrigerators = Refrigerators::stdin(self.options.refrigerator_settings());
+        let mut output = Output::stdout(self.options.output_format, self.options.bytes);
+        self.run_entry_with(entry, &mut refrigerators, &mut output)
+    }
+
+    /// Runs the recipe with the given name as if it were the main recipe, reading from and serving to the given places.
+    /// Recipe names are matched case-insensitively.
+    /// Returns the exit code that cooking has been ended with by `Throw away`, if it has.
+    pub fn run_entry_with(&self, entry: &str, refrigerators: &mut Refrigerators, output: &mut Output) -> InterpreterResult<Option<i32>> {
+        let result = self.start(entry, refrigerators, output)
+            .and_then(|mut execution| execution.run());
+        output.finish()?;
+        result
+    }
+
+    /// Prepares the kitchen and starts cooking the recipe with the given name as the main recipe,
+    /// without executing any of its statements yet.
+    /// Recipe names are matched case-insensitively.
+    pub fn start<'a>(&'a self, entry: &str, refrigerators: &'a mut Refrigerators, output: &'a mut Output) -> InterpreterResult<Execution<'a>> {
+        let entry = entry.trim().to_lowercase();
+        if self.recipe(&entry).is_none() {
+            let known: Vec<&str> = self.recipes.keys().map(String::as_str).collect();
+            return Err(format!("unknown recipe: {} (the recipes are: {})", entry, known.join(", ")).into());
+        }
+        if let Some(input) = &self.options.kitchen.input {
+            let refrigerator = refrigerators.stdin_refrigerator().unwrap_or(1);
+            refrigerators.get_mut(refrigerator)
+                .map_err(|err| format!("cannot put the input into a refrigerator: {}", err.message))?
+                .preload(input.as_bytes());
+        }
+        let initial_values = normalized_ingredients(self.options.kitchen.ingredients.clone())?;
+        Execution::new(self, &entry, self.options.kitchen.mixing_bowls(), BTreeMap::new(), &initial_values, refrigerators, output)
+    }
+
+    /// Continues cooking where a snapshot has been taken, see [`Execution::resume`].
+    pub fn resume<'a>(&'a self, snapshot: &Snapshot, refrigerators: &'a mut Refrigerators, output: &'a mut Output) -> InterpreterResult<Execution<'a>> {
+        Execution::resume(self, snapshot, refrigerators, output)
+    }
+
+    /// Runs a recipe on copies of the given mixing bowls and baking dishes and returns its first mixing bowl.
+    /// `initial_values` replace the initial values of the recipe's ingredients.
+    pub fn run_recipe(&self, recipe_name: &str, mixing_bowls: BTreeMap<MixingBowlId, MixingBowl>, baking_dishes: BTreeMap<BakingDishId, BakingDish>, initial_values: &HashMap<String, f64>, refrigerators: &mut Refrigerators, output: &mut Output) -> InterpreterResult<Option<MixingBowl>> {
+        let mut execution = Execution::new(self, recipe_name, mixing_bowls, baking_dishes, initial_values, refrigerators, output)?;
+        execution.run()?;
+        Ok(execution.result().cloned())
+    }
+
+    pub(crate) fn notify(&self, event: impl Fn(&dyn InterpreterObserver)) {
+        for observer in &self.observers {
+            event(observer.as_ref());
+        }
+    }
 }
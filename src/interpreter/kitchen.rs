@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::util::MixingBowlId;
+use crate::interpreter::ingredient::Ingredient;
+use crate::interpreter::interpreter::MixingBowl;
+
+/// The state the kitchen is in before the main recipe starts.
+///
+/// As JSON, this looks like:
+///
+/// ```json
+/// {
+///   "ingredients": { "flour": 42 },
+///   "bowls": { "1": [3, 4, "a"] },
+///   "input": "some text"
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KitchenSetup {
+    /// Initial values of the main recipe's ingredients, replacing the ones from its ingredient list.
+    pub ingredients: HashMap<String, f64>,
+    /// Prefilled mixing bowls, listed from the bottom to the top.
+    pub bowls: BTreeMap<MixingBowlId, Vec<PresetValue>>,
+    /// Text that is put into the refrigerator stocked from stdin before anything is read from stdin.
+    /// Without such a refrigerator, it goes into the first one.
+    pub input: Option<String>,
+}
+
+impl KitchenSetup {
+    pub fn load(path: &Path) -> Result<KitchenSetup, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read kitchen {}: {}", path.display(), err))?;
+        let mut kitchen: KitchenSetup = serde_json::from_str(&json)
+            .map_err(|err| format!("failed to parse kitchen {}: {}", path.display(), err))?;
+        kitchen.ingredients = normalized_ingredients(kitchen.ingredients)
+            .map_err(|err| format!("invalid kitchen {}: {}", path.display(), err))?;
+        Ok(kitchen)
+    }
+
+    pub fn mixing_bowls(&self) -> BTreeMap<MixingBowlId, MixingBowl> {
+        self.bowls.iter()
+            .map(|(id, values)| (*id, values.iter().map(PresetValue::to_ingredient).collect()))
+            .collect()
+    }
+}
+
+/// Lowercases the names of initial ingredient values, since ingredients are matched case-insensitively.
+/// Fails if two names only differ in case.
+pub fn normalized_ingredients(ingredients: impl IntoIterator<Item=(String, f64)>) -> Result<HashMap<String, f64>, String> {
+    let mut normalized = HashMap::new();
+    for (name, value) in ingredients {
+        let name = name.to_lowercase();
+        if normalized.insert(name.clone(), value).is_some() {
+            return Err(format!("ingredient {} is set more than once", name));
+        }
+    }
+    Ok(normalized)
+}
+
+/// A value in a prefilled mixing bowl: numbers are dry, single characters are liquid.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PresetValue {
+    Number(f64),
+    Char(char),
+}
+
+impl PresetValue {
+    pub fn to_ingredient(&self) -> Ingredient {
+        match self {
+            PresetValue::Number(value) => Ingredient { value: *value, liquid: false },
+            PresetValue::Char(c) => Ingredient { value: *c as u32 as f64, liquid: true },
+        }
+    }
+}
+
+/// An initial ingredient value, given on the command line as `<ingredient>=<value>`, e.g. `flour=42`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientPreset {
+    pub ingredient: String,
+    pub value: f64,
+}
+
+impl FromStr for IngredientPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ingredient, value) = s.rsplit_once('=')
+            .ok_or_else(|| format!("expected <ingredient>=<value>, found {}", s))?;
+        Ok(IngredientPreset {
+            ingredient: ingredient.trim().to_string(),
+            value: value.trim().parse().map_err(|err| format!("invalid value for {}: {}", ingredient, err))?,
+        })
+    }
+}
+
+/// A prefilled mixing bowl, given on the command line as `<bowl>=<values>`, e.g. `1=3,4,5`.
+/// The values are listed from the bottom to the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BowlPreset {
+    pub bowl: MixingBowlId,
+    pub values: Vec<f64>,
+}
+
+impl FromStr for BowlPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bowl, values) = s.split_once('=')
+            .ok_or_else(|| format!("expected <bowl>=<values>, found {}", s))?;
+        Ok(BowlPreset {
+            bowl: bowl.trim().parse().map_err(|err| format!("invalid mixing bowl number {}: {}", bowl, err))?,
+            values: values.split(',')
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| value.trim().parse().map_err(|err| format!("invalid value {} for mixing bowl {}: {}", value, bowl, err)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ingredient_names_are_lowercased() {
+        let ingredients = normalized_ingredients([("Flour".to_string(), 9.0), ("eggs".to_string(), 2.0)]).unwrap();
+        assert_eq!(ingredients, HashMap::from([("flour".to_string(), 9.0), ("eggs".to_string(), 2.0)]));
+    }
+
+    #[test]
+    fn ingredient_names_differing_in_case_are_rejected() {
+        let err = normalized_ingredients([("flour".to_string(), 9.0), ("FLOUR".to_string(), 10.0)]).unwrap_err();
+        assert_eq!(err, "ingredient flour is set more than once");
+    }
+}
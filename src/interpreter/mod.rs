@@ -1,3 +1,13 @@
+#[allow(clippy::module_inception)]
 pub mod interpreter;
+pub mod backtrace;
+pub mod examine;
+pub mod execution;
 pub mod ingredient;
+pub mod kitchen;
+pub mod number;
+pub mod observer;
+pub mod output;
+pub mod refrigerator;
+pub mod snapshot;
 mod util;
@@ -0,0 +1,243 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Decides what `Take` does with characters in front of the next number.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JunkPolicy {
+    /// Only whitespace may precede a number, anything else is an error.
+    Strict,
+    /// Anything that doesn't start a number is skipped.
+    #[default]
+    Lenient,
+}
+
+impl FromStr for JunkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "strict" => Ok(JunkPolicy::Strict),
+            "lenient" => Ok(JunkPolicy::Lenient),
+            _ => Err(format!("unknown junk policy: {} (expected strict or lenient)", s)),
+        }
+    }
+}
+
+impl Display for JunkPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JunkPolicy::Strict => write!(f, "strict"),
+            JunkPolicy::Lenient => write!(f, "lenient"),
+        }
+    }
+}
+
+/// A number found in a line of input.
+/// `start` and `end` are offsets into the scanned line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberToken {
+    pub value: f64,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds the next number in `line`, skipping whatever the policy allows.
+///
+/// Returns `Ok(None)` if the line holds no (further) number.
+pub fn find_number(line: &[u8], policy: JunkPolicy) -> Result<Option<NumberToken>, String> {
+    match policy {
+        JunkPolicy::Strict => {
+            let start = line.iter().position(|sym| !sym.is_ascii_whitespace()).unwrap_or(line.len());
+            if start == line.len() {
+                return Ok(None);
+            }
+            scan_number(line, start)?
+                .map(Some)
+                .ok_or_else(|| format!("expected a number in the refrigerator, found {:?}", decode_char(&line[start..]).0))
+        }
+        JunkPolicy::Lenient => {
+            for (start, sym) in line.iter().enumerate() {
+                if sym.is_ascii_digit() || *sym == b'+' || *sym == b'-' || *sym == b'.' {
+                    if let Some(token) = scan_number(line, start)? {
+                        return Ok(Some(token));
+                    }
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Decodes the UTF-8 character at the start of `bytes` and returns it with its length.
+/// Invalid sequences yield a replacement character for their first byte.
+pub fn decode_char(bytes: &[u8]) -> (char, usize) {
+    for len in 1..=bytes.len().min(4) {
+        if let Ok(decoded) = std::str::from_utf8(&bytes[..len]) {
+            if let Some(c) = decoded.chars().next() {
+                return (c, len);
+            }
+        }
+    }
+    (char::REPLACEMENT_CHARACTER, 1)
+}
+
+/// Scans a number starting exactly at `start`.
+///
+/// The accepted grammar is:
+///
+/// ```text
+/// number   = sign? mantissa exponent?
+/// sign     = "+" | "-"
+/// mantissa = digits ("." digits?)? | "." digits
+/// digits   = digit ("_"? digit)*
+/// exponent = ("e" | "E") sign? digit+
+/// ```
+///
+/// Only ASCII digits count as digits; `_` groups digits and is ignored.
+/// An exponent marker that isn't followed by digits is not part of the number.
+/// Numbers too large for a `f64` are returned as infinity.
+fn scan_number(bytes: &[u8], start: usize) -> Result<Option<NumberToken>, String> {
+    let mut pos = start;
+    let mut literal = String::new();
+
+    if pos < bytes.len() && (bytes[pos] == b'+' || bytes[pos] == b'-') {
+        literal.push(bytes[pos] as char);
+        pos += 1;
+    }
+
+    let int_digits = scan_digits(bytes, &mut pos, &mut literal);
+    let mut frac_digits = 0;
+    if pos < bytes.len() && bytes[pos] == b'.' {
+        let mut frac_pos = pos + 1;
+        let mut frac_literal = String::new();
+        frac_digits = scan_digits(bytes, &mut frac_pos, &mut frac_literal);
+        if int_digits > 0 || frac_digits > 0 {
+            literal.push('.');
+            literal.push_str(&frac_literal);
+            pos = frac_pos;
+        }
+    }
+    if int_digits == 0 && frac_digits == 0 {
+        return Ok(None);
+    }
+
+    if pos < bytes.len() && (bytes[pos] == b'e' || bytes[pos] == b'E') {
+        let mut exp_pos = pos + 1;
+        let mut exp_literal = String::from("e");
+        if exp_pos < bytes.len() && (bytes[exp_pos] == b'+' || bytes[exp_pos] == b'-') {
+            exp_literal.push(bytes[exp_pos] as char);
+            exp_pos += 1;
+        }
+        let exp_start = exp_pos;
+        while exp_pos < bytes.len() && bytes[exp_pos].is_ascii_digit() {
+            exp_literal.push(bytes[exp_pos] as char);
+            exp_pos += 1;
+        }
+        if exp_pos > exp_start {
+            literal.push_str(&exp_literal);
+            pos = exp_pos;
+        }
+    }
+
+    let value: f64 = literal.parse()
+        .map_err(|err| format!("failed to parse number {:?}: {}", literal, err))?;
+    Ok(Some(NumberToken { value, start, end: pos }))
+}
+
+/// Scans a run of digits that may be grouped by single underscores.
+/// Returns the number of digits found.
+fn scan_digits(bytes: &[u8], pos: &mut usize, literal: &mut String) -> usize {
+    let mut count = 0;
+    while *pos < bytes.len() {
+        if bytes[*pos].is_ascii_digit() {
+            literal.push(bytes[*pos] as char);
+            count += 1;
+            *pos += 1;
+        } else if bytes[*pos] == b'_' && count > 0 && *pos + 1 < bytes.len() && bytes[*pos + 1].is_ascii_digit() {
+            *pos += 1;
+        } else {
+            break;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines with the number that both policies find in them and where it is.
+    const ACCEPTED: &[(&str, f64, usize, usize)] = &[
+        ("42", 42.0, 0, 2),
+        ("  -7\n", -7.0, 2, 4),
+        ("+3.5 8", 3.5, 0, 4),
+        ("1_000_000", 1_000_000.0, 0, 9),
+        (".5", 0.5, 0, 2),
+        ("5.", 5.0, 0, 2),
+        ("1e3", 1000.0, 0, 3),
+        ("2E-2", 0.02, 0, 4),
+        ("6e+1x", 60.0, 0, 4),
+        ("1e", 1.0, 0, 1),
+        ("1e-", 1.0, 0, 1),
+        ("1__0", 1.0, 0, 1),
+        ("1_", 1.0, 0, 1),
+        ("2.7_5", 2.75, 0, 5),
+        ("\t0012", 12.0, 1, 5),
+        ("1e999", f64::INFINITY, 0, 5),
+    ];
+
+    #[test]
+    fn accepted_numbers() {
+        for (line, value, start, end) in ACCEPTED {
+            for policy in [JunkPolicy::Strict, JunkPolicy::Lenient] {
+                let token = find_number(line.as_bytes(), policy).unwrap()
+                    .unwrap_or_else(|| panic!("no number in {:?} with {} junk policy", line, policy));
+                assert_eq!(token, NumberToken { value: *value, start: *start, end: *end }, "{:?} with {} junk policy", line, policy);
+            }
+        }
+    }
+
+    /// A number and where it starts and ends.
+    type Found = (f64, usize, usize);
+
+    /// Lines with the number that the lenient policy finds in them and where it is,
+    /// and the character the strict policy fails on.
+    const JUNK: &[(&str, Option<Found>, char)] = &[
+        ("abc 12", Some((12.0, 4, 6)), 'a'),
+        ("_1", Some((1.0, 1, 2)), '_'),
+        ("- 5", Some((5.0, 2, 3)), '-'),
+        ("x-.5", Some((-0.5, 1, 4)), 'x'),
+        (".", None, '.'),
+        ("e5", Some((5.0, 1, 2)), 'e'),
+        ("\u{661}\u{662}", None, '\u{661}'),
+        ("\u{ff11}", None, '\u{ff11}'),
+        ("\u{e9}7", Some((7.0, 2, 3)), '\u{e9}'),
+    ];
+
+    #[test]
+    fn junk_in_front_of_numbers() {
+        for (line, lenient, strict) in JUNK {
+            let token = find_number(line.as_bytes(), JunkPolicy::Lenient).unwrap();
+            assert_eq!(token, lenient.map(|(value, start, end)| NumberToken { value, start, end }), "{:?}", line);
+            let err = find_number(line.as_bytes(), JunkPolicy::Strict).unwrap_err();
+            assert_eq!(err, format!("expected a number in the refrigerator, found {:?}", strict), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn lines_without_numbers() {
+        for line in ["", "   ", "\n", " \t\r\n"] {
+            for policy in [JunkPolicy::Strict, JunkPolicy::Lenient] {
+                assert_eq!(find_number(line.as_bytes(), policy), Ok(None), "{:?} with {} junk policy", line, policy);
+            }
+        }
+        assert_eq!(find_number(b"no numbers here", JunkPolicy::Lenient), Ok(None));
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported_as_a_replacement_character() {
+        assert_eq!(decode_char(&[0xff, b'1']), (char::REPLACEMENT_CHARACTER, 1));
+        assert_eq!(decode_char("\u{e9}".as_bytes()), ('\u{e9}', 2));
+        assert_eq!(find_number(&[0xff, b'1'], JunkPolicy::Lenient).unwrap().map(|token| token.start), Some(1));
+    }
+}
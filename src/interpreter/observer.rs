@@ -0,0 +1,183 @@
+use crate::ast::statement::SpannedStatement;
+use crate::ast::util::{BakingDishId, MixingBowlId, RefrigeratorId};
+use crate::interpreter::ingredient::Ingredient;
+
+/// Gets told about everything that happens in the kitchen.
+///
+/// Observers are registered with [`Interpreter::add_observer`](crate::interpreter::interpreter::Interpreter::add_observer).
+/// All methods do nothing by default, so an observer only needs to implement the events it is interested in.
+/// Events about ingredients, mixing bowls and baking dishes refer to the recipe that has been entered last and not exited yet.
+#[allow(unused_variables)]
+pub trait InterpreterObserver {
+    /// A recipe is about to be cooked, either as the main recipe or through `Serve with`.
+    fn recipe_entered(&self, recipe: &str) {}
+
+    /// A recipe has been cooked without errors.
+    fn recipe_exited(&self, recipe: &str) {}
+
+    /// A statement is about to be executed.
+    fn statement_started(&self, recipe: &str, statement: &SpannedStatement) {}
+
+    /// A statement has been executed without errors. For loops, this happens after the last iteration.
+    fn statement_finished(&self, recipe: &str, statement: &SpannedStatement) {}
+
+    /// The body of a loop is about to run. `iteration` starts at 1.
+    fn loop_iterated(&self, recipe: &str, statement: &SpannedStatement, iteration: usize) {}
+
+    /// An ingredient got a new value or became liquid.
+    fn ingredient_changed(&self, ingredient: &str, value: &Ingredient) {}
+
+    /// An ingredient has been put on top of a mixing bowl.
+    fn pushed(&self, mixing_bowl: MixingBowlId, ingredient: &Ingredient) {}
+
+    /// The top ingredient of a mixing bowl has been taken off.
+    fn popped(&self, mixing_bowl: MixingBowlId, ingredient: &Ingredient) {}
+
+    /// A mixing bowl has been cleaned.
+    fn bowl_cleared(&self, mixing_bowl: MixingBowlId) {}
+
+    /// A mixing bowl has been shaken. `contents` are listed from the bottom to the top.
+    fn bowl_sorted(&self, mixing_bowl: MixingBowlId, contents: &[Ingredient]) {}
+
+    /// A mixing bowl has been mixed. `contents` are listed from the bottom to the top.
+    fn bowl_shuffled(&self, mixing_bowl: MixingBowlId, contents: &[Ingredient]) {}
+
+    /// The contents of a mixing bowl changed in some other way, e.g. by adding to its top or stirring it.
+    /// `contents` are listed from the bottom to the top.
+    fn bowl_changed(&self, mixing_bowl: MixingBowlId, contents: &[Ingredient]) {}
+
+    /// A mixing bowl has been poured into a baking dish. `dish` is the whole baking dish afterwards.
+    fn dish_poured(&self, mixing_bowl: MixingBowlId, baking_dish: BakingDishId, dish: &[Ingredient]) {}
+
+    /// A value has been taken from a refrigerator.
+    fn input_consumed(&self, refrigerator: RefrigeratorId, value: &Ingredient) {}
+
+    /// A baking dish has been served.
+    fn output_produced(&self, baking_dish: BakingDishId, dish: &[Ingredient]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use crate::ast::recipe::recipes_from;
+    use crate::grammar::parse;
+    use crate::interpreter::interpreter::Interpreter;
+    use crate::interpreter::output::{MemorySink, Output, OutputFormat};
+    use crate::interpreter::refrigerator::{RefrigeratorSettings, Refrigerators};
+
+    use super::*;
+
+    /// Writes down every event but the start and end of statements.
+    #[derive(Default)]
+    struct Recorder {
+        events: RefCell<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn record(&self, event: String) {
+            self.events.borrow_mut().push(event);
+        }
+    }
+
+    impl InterpreterObserver for Recorder {
+        fn recipe_entered(&self, recipe: &str) {
+            self.record(format!("enter {}", recipe));
+        }
+
+        fn recipe_exited(&self, recipe: &str) {
+            self.record(format!("exit {}", recipe));
+        }
+
+        fn loop_iterated(&self, recipe: &str, statement: &SpannedStatement, iteration: usize) {
+            self.record(format!("loop {} at {} #{}", recipe, statement.span(), iteration));
+        }
+
+        fn ingredient_changed(&self, ingredient: &str, value: &Ingredient) {
+            self.record(format!("{} = {}", ingredient, value.value));
+        }
+
+        fn pushed(&self, mixing_bowl: MixingBowlId, ingredient: &Ingredient) {
+            self.record(format!("push {} onto {}", ingredient.value, mixing_bowl));
+        }
+
+        fn popped(&self, mixing_bowl: MixingBowlId, ingredient: &Ingredient) {
+            self.record(format!("pop {} from {}", ingredient.value, mixing_bowl));
+        }
+
+        fn dish_poured(&self, mixing_bowl: MixingBowlId, baking_dish: BakingDishId, dish: &[Ingredient]) {
+            self.record(format!("pour {} into {}: {:?}", mixing_bowl, baking_dish, dish.iter().map(|i| i.value).collect::<Vec<_>>()));
+        }
+
+        fn input_consumed(&self, refrigerator: RefrigeratorId, value: &Ingredient) {
+            self.record(format!("take {} from {}", value.value, refrigerator));
+        }
+
+        fn output_produced(&self, baking_dish: BakingDishId, dish: &[Ingredient]) {
+            self.record(format!("serve {}: {:?}", baking_dish, dish.iter().map(|i| i.value).collect::<Vec<_>>()));
+        }
+    }
+
+    const OBSERVED: &str = "Observed.
+
+Ingredients.
+0 g number
+2 g rounds
+
+Method.
+Take number from refrigerator.
+Chop the rounds.
+Put number into the mixing bowl.
+Chop the rounds until chopped.
+Fold number into the mixing bowl.
+Serve with helper.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Helper.
+
+Ingredients.
+1 g one
+
+Method.
+Put one into the mixing bowl.
+";
+
+    #[test]
+    fn events_are_told_in_order() {
+        let recipes = recipes_from(parse(OBSERVED).unwrap().next().unwrap()).unwrap();
+        let recorder = Rc::new(Recorder::default());
+        let mut interpreter = Interpreter::new(recipes);
+        interpreter.add_observer(recorder.clone());
+        let mut refrigerators = Refrigerators::new(RefrigeratorSettings::default());
+        refrigerators.stock(1, Box::new(Cursor::new(b"7\n".to_vec())));
+        let mut output = Output::new(Box::new(MemorySink::default()), OutputFormat::Spec, false);
+        interpreter.run_main_with(&mut refrigerators, &mut output).unwrap();
+
+        assert_eq!(*recorder.events.borrow(), vec![
+            "enter observed",
+            "take 7 from 1",
+            "number = 7",
+            "loop observed at 9:1 #1",
+            "push 7 onto 1",
+            "rounds = 1",
+            "loop observed at 9:1 #2",
+            "push 7 onto 1",
+            "rounds = 0",
+            "pop 7 from 1",
+            "number = 7",
+            "enter helper",
+            "push 1 onto 1",
+            "exit helper",
+            // The first mixing bowl of the helper ends up on top of the caller's.
+            "push 7 onto 1",
+            "push 1 onto 1",
+            "pour 1 into 1: [1.0, 7.0, 7.0]",
+            "serve 1: [1.0, 7.0, 7.0]",
+            "exit observed",
+        ]);
+    }
+}
@@ -0,0 +1,383 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::ast::util::BakingDishId;
+use crate::interpreter::ingredient::Ingredient;
+use crate::interpreter::interpreter::{InterpreterError, InterpreterResult};
+
+/// How served dishes are written.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Liquids as characters, dry values as numbers separated by single spaces, every dish on its own line.
+    #[default]
+    Spec,
+    /// Every value on its own line.
+    Lines,
+    /// A JSON array of all served dishes, written once the recipe has finished.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "spec" => Ok(OutputFormat::Spec),
+            "lines" => Ok(OutputFormat::Lines),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown output format: {} (expected spec, lines or json)", s)),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Spec => write!(f, "spec"),
+            OutputFormat::Lines => write!(f, "lines"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DishItem {
+    Number(serde_json::Number),
+    Char(char),
+    Byte(u8),
+}
+
+impl DishItem {
+    fn from(ingredient: &Ingredient, bytes: bool) -> InterpreterResult<DishItem> {
+        if ingredient.liquid && bytes {
+            return byte(ingredient).map(DishItem::Byte);
+        }
+        if ingredient.liquid && ingredient.value >= 0.0 {
+            if let Some(c) = char::from_u32(ingredient.value as u32) {
+                return Ok(DishItem::Char(c));
+            }
+        }
+        json_number(ingredient.value).map(DishItem::Number)
+    }
+}
+
+/// The byte a liquid is served as in byte mode, with its fraction cut off.
+/// Values that don't fit into a byte, like the sentinel for an empty refrigerator, can't be served.
+fn byte(ingredient: &Ingredient) -> InterpreterResult<u8> {
+    if (0.0..256.0).contains(&ingredient.value) {
+        Ok(ingredient.value as u8)
+    } else {
+        Err(format!("cannot serve {} as a byte, only liquids from 0 to 255 can be served in byte mode", ingredient.value).into())
+    }
+}
+
+/// The JSON number a value is served as, without a fraction if it doesn't have one.
+/// JSON has no numbers for NaN and the infinities, so they can't be served.
+fn json_number(value: f64) -> InterpreterResult<serde_json::Number> {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Ok((value as i64).into())
+    } else {
+        serde_json::Number::from_f64(value)
+            .ok_or_else(|| format!("cannot serve {} as JSON, only finite numbers can be served in json mode", value).into())
+    }
+}
+
+/// Where the dishes routed to it end up, given on the command line as `stdout`, `stderr` or a file path.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SinkTarget {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+impl SinkTarget {
+    pub fn open(&self) -> std::io::Result<Box<dyn Write>> {
+        Ok(match self {
+            SinkTarget::Stdout => Box::new(std::io::stdout()),
+            SinkTarget::Stderr => Box::new(std::io::stderr()),
+            SinkTarget::File(path) => Box::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+}
+
+impl FromStr for SinkTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("missing output target".to_string()),
+            "stdout" | "-" => Ok(SinkTarget::Stdout),
+            "stderr" => Ok(SinkTarget::Stderr),
+            path => Ok(SinkTarget::File(PathBuf::from(path))),
+        }
+    }
+}
+
+/// A sink that keeps everything written to it, e.g. to compare what has been served with what was expected.
+/// Clones share their contents.
+#[derive(Clone, Debug, Default)]
+pub struct MemorySink(Rc<RefCell<Vec<u8>>>);
+
+impl MemorySink {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+}
+
+impl Write for MemorySink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Routes a baking dish to a sink, given on the command line as `<dish>=<target>`, e.g. `2=stderr`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DishRoute {
+    pub dish: BakingDishId,
+    pub target: SinkTarget,
+}
+
+impl FromStr for DishRoute {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (dish, target) = s.split_once('=')
+            .ok_or_else(|| format!("expected <dish>=<target>, found {}", s))?;
+        Ok(DishRoute {
+            dish: dish.trim().parse().map_err(|err| format!("invalid baking dish number {}: {}", dish, err))?,
+            target: target.parse()?,
+        })
+    }
+}
+
+struct Sink {
+    writer: Box<dyn Write>,
+    served: Vec<Vec<DishItem>>,
+}
+
+impl Sink {
+    fn new(writer: Box<dyn Write>) -> Sink {
+        Sink {
+            writer,
+            served: Vec::new(),
+        }
+    }
+}
+
+/// Where served dishes end up.
+///
+/// Every dish is written to the default sink unless it has been routed to another one.
+/// Whatever `Examine` finds goes to a separate debug sink, stderr by default.
+pub struct Output {
+    format: OutputFormat,
+    bytes: bool,
+    sinks: Vec<Sink>,
+    routes: BTreeMap<BakingDishId, usize>,
+    debug: Option<Box<dyn Write>>,
+}
+
+impl Output {
+    /// Creates an output with the given default sink.
+    /// With `bytes`, liquid ingredients are written as single raw bytes instead of UTF-8 encoded characters.
+    pub fn new(sink: Box<dyn Write>, format: OutputFormat, bytes: bool) -> Output {
+        Output {
+            format,
+            bytes,
+            sinks: vec![Sink::new(sink)],
+            routes: BTreeMap::new(),
+            debug: Some(Box::new(std::io::stderr())),
+        }
+    }
+
+    pub fn stdout(format: OutputFormat, bytes: bool) -> Output {
+        Output::new(Box::new(std::io::stdout()), format, bytes)
+    }
+
+    /// Adds another sink that dishes can be routed to and returns its index.
+    pub fn add_sink(&mut self, sink: Box<dyn Write>) -> usize {
+        self.sinks.push(Sink::new(sink));
+        self.sinks.len() - 1
+    }
+
+    /// Sends the given baking dish to a sink previously returned by [`Output::add_sink`].
+    /// Index 0 is the default sink.
+    pub fn route_dish(&mut self, dish: BakingDishId, sink: usize) {
+        self.routes.insert(dish, sink);
+    }
+
+    /// Opens the targets of all routes and routes the dishes there.
+    /// Dishes with the same target share one sink.
+    pub fn add_routes(&mut self, routes: &[DishRoute]) -> std::io::Result<()> {
+        let mut opened: HashMap<&SinkTarget, usize> = HashMap::new();
+        for route in routes {
+            let sink = match opened.get(&route.target) {
+                Some(sink) => *sink,
+                None => {
+                    let sink = self.add_sink(route.target.open()?);
+                    opened.insert(&route.target, sink);
+                    sink
+                }
+            };
+            self.route_dish(route.dish, sink);
+        }
+        Ok(())
+    }
+
+    /// Replaces the debug sink. Without one, `Examine` is silent.
+    pub fn set_debug_sink(&mut self, sink: Option<Box<dyn Write>>) {
+        self.debug = sink;
+    }
+
+    /// Writes a line to the debug sink, if there is one.
+    pub fn debug(&mut self, text: &str) -> InterpreterResult<()> {
+        if let Some(debug) = &mut self.debug {
+            writeln!(debug, "{}", text)
+                .and_then(|_| debug.flush())
+                .map_err(|err| format!("failed to examine the kitchen: {}", err))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the ingredients of a dish in serving order.
+    pub fn serve(&mut self, dish_id: BakingDishId, dish: &[Ingredient]) -> InterpreterResult<()> {
+        let sink = &mut self.sinks[self.routes.get(&dish_id).copied().unwrap_or(0)];
+        match self.format {
+            OutputFormat::Spec => {
+                let mut after_dry = false;
+                for ingredient in dish {
+                    if !ingredient.liquid && after_dry {
+                        write!(sink.writer, " ").map_err(Self::write_error)?;
+                    }
+                    Self::write_ingredient(&mut sink.writer, ingredient, self.bytes)?;
+                    after_dry = !ingredient.liquid;
+                }
+                // Raw bytes are served as they are, but dishes without anything raw still end their line.
+                if !self.bytes || dish.iter().all(|ingredient| !ingredient.liquid) {
+                    writeln!(sink.writer).map_err(Self::write_error)?;
+                }
+            }
+            OutputFormat::Lines => {
+                for ingredient in dish {
+                    Self::write_ingredient(&mut sink.writer, ingredient, self.bytes)?;
+                    writeln!(sink.writer).map_err(Self::write_error)?;
+                }
+            }
+            OutputFormat::Json => {
+                let items = dish.iter()
+                    .map(|ingredient| DishItem::from(ingredient, self.bytes))
+                    .collect::<InterpreterResult<_>>()?;
+                sink.served.push(items);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes anything that is held back until the end and flushes all sinks.
+    /// As JSON, the default sink always gets a list of dishes, other sinks only if something has been served there.
+    pub fn finish(&mut self) -> InterpreterResult<()> {
+        for (index, sink) in self.sinks.iter_mut().enumerate() {
+            if self.format == OutputFormat::Json && (index == 0 || !sink.served.is_empty()) {
+                serde_json::to_writer(&mut sink.writer, &sink.served)
+                    .map_err(|err| format!("failed to serve the dishes: {}", err))?;
+                writeln!(sink.writer).map_err(Self::write_error)?;
+                sink.served.clear();
+            }
+            sink.writer.flush().map_err(Self::write_error)?;
+        }
+        Ok(())
+    }
+
+    fn write_ingredient(writer: &mut Box<dyn Write>, ingredient: &Ingredient, bytes: bool) -> InterpreterResult<()> {
+        if ingredient.liquid && bytes {
+            writer.write_all(&[byte(ingredient)?])
+        } else {
+            write!(writer, "{}", ingredient)
+        }.map_err(Self::write_error)
+    }
+
+    fn write_error(err: std::io::Error) -> InterpreterError {
+        format!("failed to serve the dishes: {}", err).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dry(value: f64) -> Ingredient {
+        Ingredient { value, liquid: false }
+    }
+
+    fn liquid(c: char) -> Ingredient {
+        Ingredient { value: c as u32 as f64, liquid: true }
+    }
+
+    /// Serves the given dishes to dish 1 and returns what has been written.
+    fn serve(format: OutputFormat, bytes: bool, dishes: &[&[Ingredient]]) -> InterpreterResult<Vec<u8>> {
+        let served = MemorySink::default();
+        let mut output = Output::new(Box::new(served.clone()), format, bytes);
+        for dish in dishes {
+            output.serve(1, dish)?;
+        }
+        output.finish()?;
+        Ok(served.contents())
+    }
+
+    #[test]
+    fn spec_separates_adjacent_dry_values() {
+        let dish = [dry(1.0), dry(2.5), liquid('a'), liquid('b'), dry(-3.0), liquid('c'), dry(4.0)];
+        assert_eq!(serve(OutputFormat::Spec, false, &[&dish, &[]]).unwrap(), b"1 2.5ab-3c4\n\n");
+    }
+
+    #[test]
+    fn spec_leaves_out_the_line_break_after_bytes() {
+        let dish = [Ingredient { value: 233.0, liquid: true }, dry(7.0)];
+        assert_eq!(serve(OutputFormat::Spec, true, &[&dish, &[dry(1.0), dry(2.0)]]).unwrap(), b"\xe971 2\n");
+    }
+
+    #[test]
+    fn lines_write_every_value_on_its_own_line() {
+        let dish = [dry(1.0), liquid('a'), dry(-2.5)];
+        assert_eq!(serve(OutputFormat::Lines, false, &[&dish, &[dry(3.0)]]).unwrap(), b"1\na\n-2.5\n3\n");
+    }
+
+    #[test]
+    fn json_rejects_numbers_that_are_not_finite() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let err = serve(OutputFormat::Json, false, &[&[dry(value)]]).unwrap_err();
+            assert_eq!(err.message, format!("cannot serve {} as JSON, only finite numbers can be served in json mode", value));
+        }
+        assert_eq!(serve(OutputFormat::Json, false, &[&[dry(2.0), dry(0.5), liquid('a')]]).unwrap(), b"[[{\"number\":2},{\"number\":0.5},{\"char\":\"a\"}]]\n");
+    }
+
+    #[test]
+    fn json_skips_route_sinks_without_dishes() {
+        let default = MemorySink::default();
+        let served = MemorySink::default();
+        let unused = MemorySink::default();
+        let mut output = Output::new(Box::new(default.clone()), OutputFormat::Json, false);
+        let served_sink = output.add_sink(Box::new(served.clone()));
+        let unused_sink = output.add_sink(Box::new(unused.clone()));
+        output.route_dish(2, served_sink);
+        output.route_dish(3, unused_sink);
+        output.serve(2, &[Ingredient { value: 7.0, liquid: false }]).unwrap();
+        output.finish().unwrap();
+        assert_eq!(default.contents(), b"[]\n");
+        assert_eq!(served.contents(), b"[[{\"number\":7}]]\n");
+        assert_eq!(unused.contents(), b"");
+    }
+}
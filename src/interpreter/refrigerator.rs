@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::util::RefrigeratorId;
+use crate::interpreter::interpreter::InterpreterResult;
+use crate::interpreter::number::{decode_char, find_number, JunkPolicy};
+
+/// Decides what `Take` and `Check` do once the input source has been used up for good.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EofBehavior {
+    /// Taking from an empty refrigerator is a runtime error.
+    #[default]
+    Error,
+    /// Taking from an empty refrigerator yields `0`.
+    Zero,
+    /// Taking from an empty refrigerator yields `-1`, which `Check` reports as well.
+    Sentinel,
+}
+
+impl EofBehavior {
+    fn value(self) -> InterpreterResult<f64> {
+        match self {
+            EofBehavior::Error => Err("the refrigerator is empty".into()),
+            EofBehavior::Zero => Ok(0.0),
+            EofBehavior::Sentinel => Ok(-1.0),
+        }
+    }
+
+    fn check_value(self) -> f64 {
+        match self {
+            EofBehavior::Sentinel => -1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl FromStr for EofBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(EofBehavior::Error),
+            "zero" | "0" => Ok(EofBehavior::Zero),
+            "sentinel" | "-1" => Ok(EofBehavior::Sentinel),
+            _ => Err(format!("unknown end of input behavior: {} (expected error, zero or sentinel)", s)),
+        }
+    }
+}
+
+impl Display for EofBehavior {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EofBehavior::Error => write!(f, "error"),
+            EofBehavior::Zero => write!(f, "zero"),
+            EofBehavior::Sentinel => write!(f, "sentinel"),
+        }
+    }
+}
+
+/// How refrigerators hand out their contents.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RefrigeratorSettings {
+    pub eof_behavior: EofBehavior,
+    pub junk_policy: JunkPolicy,
+    /// Liquid ingredients are taken as single raw bytes instead of Unicode characters.
+    pub bytes: bool,
+}
+
+/// What is left in a refrigerator, apart from whatever its source still holds.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RefrigeratorSnapshot {
+    /// The part of the current line that hasn't been taken yet.
+    pub unread: Vec<u8>,
+    /// Input that has been supplied by hand but not restocked yet.
+    pub supplied: Vec<u8>,
+    pub closed: bool,
+    pub exhausted: bool,
+}
+
+/// What restocking the refrigerator brought.
+enum Restock {
+    Restocked,
+    /// The source has nothing left.
+    Exhausted,
+    /// The refrigerator is stocked by hand and waits for more input.
+    Starved,
+}
+
+/// The input buffer that `Take` and `Check` work on, restocked line by line from its source.
+///
+/// A refrigerator without a source is stocked by hand with [`Refrigerator::supply`].
+/// Instead of blocking, it reports that it needs more input until it is [closed](Refrigerator::close).
+pub struct Refrigerator {
+    buffer: Vec<u8>,
+    position: usize,
+    source: Option<Box<dyn BufRead>>,
+    supplied: Vec<u8>,
+    closed: bool,
+    exhausted: bool,
+    settings: RefrigeratorSettings,
+}
+
+impl Refrigerator {
+    pub fn new(source: Box<dyn BufRead>, settings: RefrigeratorSettings) -> Refrigerator {
+        Refrigerator {
+            source: Some(source),
+            ..Refrigerator::by_hand(settings)
+        }
+    }
+
+    pub fn by_hand(settings: RefrigeratorSettings) -> Refrigerator {
+        Refrigerator {
+            buffer: Vec::new(),
+            position: 0,
+            source: None,
+            supplied: Vec::new(),
+            closed: false,
+            exhausted: false,
+            settings,
+        }
+    }
+
+    pub fn stdin(settings: RefrigeratorSettings) -> Refrigerator {
+        Refrigerator::new(Box::new(BufReader::new(std::io::stdin())), settings)
+    }
+
+    /// Puts something into the refrigerator in front of anything that is still in there.
+    pub fn preload(&mut self, input: &[u8]) {
+        self.buffer.splice(self.position..self.position, input.iter().copied());
+    }
+
+    /// Adds input to a refrigerator that is stocked by hand.
+    /// Like any other source, it is handed out line by line, so a line only counts once its newline has been supplied.
+    pub fn supply(&mut self, input: &[u8]) {
+        self.supplied.extend_from_slice(input);
+    }
+
+    /// Tells a refrigerator that is stocked by hand that no more input will be supplied.
+    /// A trailing line without a newline is handed out now.
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn snapshot(&self) -> RefrigeratorSnapshot {
+        RefrigeratorSnapshot {
+            unread: self.unread().to_vec(),
+            supplied: self.supplied.clone(),
+            closed: self.closed,
+            exhausted: self.exhausted,
+        }
+    }
+
+    /// Puts back what was left in the refrigerator when the snapshot was taken, replacing the current buffer.
+    /// The source stays the same, so a refrigerator with a source only counts as used up once that source is.
+    pub fn restore(&mut self, snapshot: &RefrigeratorSnapshot) {
+        self.buffer = snapshot.unread.clone();
+        self.position = 0;
+        self.supplied = snapshot.supplied.clone();
+        self.closed = snapshot.closed;
+        self.exhausted = self.source.is_none() && snapshot.exhausted;
+    }
+
+    /// The part of the current line that hasn't been taken yet.
+    pub fn unread(&self) -> &[u8] {
+        &self.buffer[self.position..]
+    }
+
+    fn take(&mut self, len: usize) {
+        self.position += len;
+    }
+
+    fn discard(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+    }
+
+    /// Appends the next line of the source to the buffer.
+    fn restock(&mut self) -> InterpreterResult<Restock> {
+        if self.exhausted {
+            return Ok(Restock::Exhausted);
+        }
+        self.buffer.drain(..self.position);
+        self.position = 0;
+        let read = match &mut self.source {
+            Some(source) => source.read_until(b'\n', &mut self.buffer)
+                .map_err(|err| format!("failed to restock the refrigerator: {}", err))?,
+            None => {
+                let len = match self.supplied.iter().position(|sym| *sym == b'\n') {
+                    Some(newline) => newline + 1,
+                    None if self.closed => self.supplied.len(),
+                    None => return Ok(Restock::Starved),
+                };
+                self.buffer.extend(self.supplied.drain(..len));
+                len
+            }
+        };
+        if read == 0 {
+            self.exhausted = true;
+            Ok(Restock::Exhausted)
+        } else {
+            Ok(Restock::Restocked)
+        }
+    }
+
+    /// Checks whether `Take` would find a number without running into the end of the input.
+    /// Lines that `Take` would skip are dropped on the way.
+    ///
+    /// Returns `None` if the refrigerator is stocked by hand and needs more input to tell.
+    pub fn check_for_number(&mut self) -> InterpreterResult<Option<f64>> {
+        loop {
+            match find_number(self.unread(), self.settings.junk_policy) {
+                Ok(Some(_)) => return Ok(Some(1.0)),
+                Ok(None) => {
+                    self.discard();
+                    match self.restock()? {
+                        Restock::Restocked => {}
+                        Restock::Exhausted => return Ok(Some(self.settings.eof_behavior.check_value())),
+                        Restock::Starved => return Ok(None),
+                    }
+                }
+                Err(_) => return Ok(Some(0.0)),
+            }
+        }
+    }
+
+    /// Checks whether `Take` would find anything for a liquid ingredient, restocking the refrigerator if it is empty.
+    ///
+    /// Returns `None` if the refrigerator is stocked by hand and needs more input to tell.
+    pub fn check_for_anything(&mut self) -> InterpreterResult<Option<f64>> {
+        while self.unread().is_empty() {
+            match self.restock()? {
+                Restock::Restocked => {}
+                Restock::Exhausted => return Ok(Some(self.settings.eof_behavior.check_value())),
+                Restock::Starved => return Ok(None),
+            }
+        }
+        Ok(Some(1.0))
+    }
+
+    /// Reads the next number.
+    /// Returns `None` if the refrigerator is stocked by hand and needs more input.
+    pub fn read_number(&mut self) -> InterpreterResult<Option<f64>> {
+        loop {
+            if let Some(token) = find_number(self.unread(), self.settings.junk_policy)? {
+                if !token.value.is_finite() {
+                    return Err(format!("number out of range: {}", String::from_utf8_lossy(&self.unread()[token.start..token.end])).into());
+                }
+                self.take(token.end);
+                return Ok(Some(token.value));
+            }
+            self.discard();
+            match self.restock()? {
+                Restock::Restocked => {}
+                Restock::Exhausted => return self.settings.eof_behavior.value().map(Some),
+                Restock::Starved => return Ok(None),
+            }
+        }
+    }
+
+    /// Reads a single Unicode character or, in byte mode, a single raw byte.
+    /// Returns `None` if the refrigerator is stocked by hand and needs more input.
+    pub fn read_char(&mut self) -> InterpreterResult<Option<f64>> {
+        while self.unread().is_empty() {
+            match self.restock()? {
+                Restock::Restocked => {}
+                Restock::Exhausted => return self.settings.eof_behavior.value().map(Some),
+                Restock::Starved => return Ok(None),
+            }
+        }
+        if self.settings.bytes {
+            let res = self.unread()[0];
+            self.take(1);
+            return Ok(Some(res as f64));
+        }
+        let (res, len) = decode_char(self.unread());
+        self.take(len);
+        Ok(Some(res as u32 as f64))
+    }
+}
+
+/// Where a refrigerator gets stocked from, given on the command line as `stdin`, `text:<literal>` or a file path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RefrigeratorSource {
+    Stdin,
+    Text(String),
+    File(PathBuf),
+}
+
+impl RefrigeratorSource {
+    pub fn open(&self) -> std::io::Result<Box<dyn BufRead>> {
+        Ok(match self {
+            RefrigeratorSource::Stdin => Box::new(BufReader::new(std::io::stdin())),
+            RefrigeratorSource::Text(text) => Box::new(Cursor::new(text.clone().into_bytes())),
+            RefrigeratorSource::File(path) => Box::new(BufReader::new(File::open(path)?)),
+        })
+    }
+}
+
+impl FromStr for RefrigeratorSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(text) = s.strip_prefix("text:") {
+            return Ok(RefrigeratorSource::Text(text.to_string()));
+        }
+        match s {
+            "" => Err("missing refrigerator source".to_string()),
+            "stdin" | "-" => Ok(RefrigeratorSource::Stdin),
+            path => Ok(RefrigeratorSource::File(PathBuf::from(path))),
+        }
+    }
+}
+
+/// Binds a refrigerator to a source, given on the command line as `<refrigerator>=<source>`, e.g. `2=input.txt`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RefrigeratorBinding {
+    pub refrigerator: RefrigeratorId,
+    pub source: RefrigeratorSource,
+}
+
+impl FromStr for RefrigeratorBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (refrigerator, source) = s.split_once('=')
+            .ok_or_else(|| format!("expected <refrigerator>=<source>, found {}", s))?;
+        Ok(RefrigeratorBinding {
+            refrigerator: refrigerator.trim().parse().map_err(|err| format!("invalid refrigerator number {}: {}", refrigerator, err))?,
+            source: source.parse()?,
+        })
+    }
+}
+
+/// All refrigerators of the kitchen by their number.
+pub struct Refrigerators {
+    refrigerators: BTreeMap<RefrigeratorId, Refrigerator>,
+    stdin_refrigerator: Option<RefrigeratorId>,
+    settings: RefrigeratorSettings,
+}
+
+impl Refrigerators {
+    /// Creates a kitchen without any stocked refrigerators.
+    pub fn new(settings: RefrigeratorSettings) -> Refrigerators {
+        Refrigerators {
+            refrigerators: BTreeMap::new(),
+            stdin_refrigerator: None,
+            settings,
+        }
+    }
+
+    /// Creates a kitchen whose first refrigerator is stocked from stdin.
+    pub fn stdin(settings: RefrigeratorSettings) -> Refrigerators {
+        let mut refrigerators = Refrigerators::new(settings);
+        refrigerators.stock(1, Box::new(BufReader::new(std::io::stdin())));
+        refrigerators.stdin_refrigerator = Some(1);
+        refrigerators
+    }
+
+    /// Stocks the given refrigerator from a source, replacing whatever it was stocked from before.
+    pub fn stock(&mut self, refrigerator: RefrigeratorId, source: Box<dyn BufRead>) {
+        if self.stdin_refrigerator == Some(refrigerator) {
+            self.stdin_refrigerator = None;
+        }
+        self.refrigerators.insert(refrigerator, Refrigerator::new(source, self.settings));
+    }
+
+    /// Makes the given refrigerator one that is stocked by hand, see [`Refrigerator::supply`].
+    pub fn stock_by_hand(&mut self, refrigerator: RefrigeratorId) {
+        if self.stdin_refrigerator == Some(refrigerator) {
+            self.stdin_refrigerator = None;
+        }
+        self.refrigerators.insert(refrigerator, Refrigerator::by_hand(self.settings));
+    }
+
+    /// Opens the sources of all bindings and stocks the refrigerators from them.
+    /// Stdin may only stock a single refrigerator. Binding it to another one leaves the refrigerator
+    /// that stdin stocked before these bindings empty.
+    pub fn add_bindings(&mut self, bindings: &[RefrigeratorBinding]) -> Result<(), String> {
+        let (stdin_bindings, other_bindings): (Vec<_>, Vec<_>) = bindings.iter()
+            .partition(|binding| binding.source == RefrigeratorSource::Stdin);
+        let mut bound_stdin = None;
+        for binding in other_bindings.into_iter().chain(stdin_bindings) {
+            if binding.source == RefrigeratorSource::Stdin {
+                if let Some(other) = bound_stdin {
+                    if other != binding.refrigerator {
+                        return Err(format!("stdin can't stock both refrigerator {} and {}", other, binding.refrigerator));
+                    }
+                }
+                if let Some(previous) = self.stdin_refrigerator {
+                    if previous != binding.refrigerator {
+                        self.refrigerators.remove(&previous);
+                        self.stdin_refrigerator = None;
+                    }
+                }
+                bound_stdin = Some(binding.refrigerator);
+            }
+            let source = binding.source.open()
+                .map_err(|err| format!("failed to stock refrigerator {}: {}", binding.refrigerator, err))?;
+            self.stock(binding.refrigerator, source);
+            if binding.source == RefrigeratorSource::Stdin {
+                self.stdin_refrigerator = Some(binding.refrigerator);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<RefrigeratorId, RefrigeratorSnapshot> {
+        self.refrigerators.iter()
+            .map(|(id, refrigerator)| (*id, refrigerator.snapshot()))
+            .collect()
+    }
+
+    /// Restores the contents of the refrigerators from a snapshot.
+    /// Refrigerators that aren't stocked yet get an empty source, so only their restored contents can be taken.
+    pub fn restore(&mut self, snapshot: &BTreeMap<RefrigeratorId, RefrigeratorSnapshot>) {
+        for (id, contents) in snapshot {
+            if !self.refrigerators.contains_key(id) {
+                self.stock(*id, Box::new(std::io::empty()));
+            }
+            self.refrigerators.get_mut(id).unwrap().restore(contents);
+        }
+    }
+
+    /// The refrigerator that is stocked from stdin, if any.
+    pub fn stdin_refrigerator(&self) -> Option<RefrigeratorId> {
+        self.stdin_refrigerator
+    }
+
+    pub fn get(&self, refrigerator: RefrigeratorId) -> Option<&Refrigerator> {
+        self.refrigerators.get(&refrigerator)
+    }
+
+    pub fn get_mut(&mut self, refrigerator: RefrigeratorId) -> InterpreterResult<&mut Refrigerator> {
+        self.refrigerators.get_mut(&refrigerator)
+            .ok_or_else(|| format!("refrigerator {} is not stocked", refrigerator).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refrigerator(input: &str, eof_behavior: EofBehavior) -> Refrigerator {
+        let settings = RefrigeratorSettings { eof_behavior, ..RefrigeratorSettings::default() };
+        Refrigerator::new(Box::new(Cursor::new(input.as_bytes().to_vec())), settings)
+    }
+
+    #[test]
+    fn error_at_eof() {
+        let mut refrigerator = refrigerator("4\n", EofBehavior::Error);
+        assert_eq!(refrigerator.read_number().unwrap(), Some(4.0));
+        assert_eq!(refrigerator.check_for_number().unwrap(), Some(0.0));
+        assert_eq!(refrigerator.check_for_anything().unwrap(), Some(0.0));
+        assert_eq!(refrigerator.read_number().unwrap_err().message, "the refrigerator is empty");
+        assert_eq!(refrigerator.read_char().unwrap_err().message, "the refrigerator is empty");
+    }
+
+    #[test]
+    fn zero_at_eof() {
+        let mut refrigerator = refrigerator("4 x\n", EofBehavior::Zero);
+        assert_eq!(refrigerator.read_number().unwrap(), Some(4.0));
+        assert_eq!(refrigerator.check_for_number().unwrap(), Some(0.0));
+        assert_eq!(refrigerator.read_number().unwrap(), Some(0.0));
+        assert_eq!(refrigerator.check_for_anything().unwrap(), Some(0.0));
+        assert_eq!(refrigerator.read_char().unwrap(), Some(0.0));
+    }
+
+    #[test]
+    fn sentinel_at_eof() {
+        let mut refrigerator = refrigerator("4\na", EofBehavior::Sentinel);
+        assert_eq!(refrigerator.read_number().unwrap(), Some(4.0));
+        assert_eq!(refrigerator.check_for_anything().unwrap(), Some(1.0));
+        assert_eq!(refrigerator.read_char().unwrap(), Some(10.0));
+        assert_eq!(refrigerator.read_char().unwrap(), Some('a' as u32 as f64));
+        assert_eq!(refrigerator.check_for_number().unwrap(), Some(-1.0));
+        assert_eq!(refrigerator.check_for_anything().unwrap(), Some(-1.0));
+        assert_eq!(refrigerator.read_number().unwrap(), Some(-1.0));
+        assert_eq!(refrigerator.read_char().unwrap(), Some(-1.0));
+    }
+
+    #[test]
+    fn check_skips_lines_without_numbers() {
+        let mut refrigerator = refrigerator("no number\n\n  7\n", EofBehavior::Error);
+        assert_eq!(refrigerator.check_for_number().unwrap(), Some(1.0));
+        assert_eq!(refrigerator.read_number().unwrap(), Some(7.0));
+    }
+}
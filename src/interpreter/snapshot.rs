@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::util::{BakingDishId, MixingBowlId, RefrigeratorId};
+use crate::interpreter::ingredient::Ingredient;
+use crate::interpreter::interpreter::{BakingDish, MixingBowl};
+use crate::interpreter::refrigerator::RefrigeratorSnapshot;
+
+/// The version of the snapshot format. Snapshots of other versions are rejected.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Everything needed to continue cooking later, taken with [`Execution::snapshot`](crate::interpreter::execution::Execution::snapshot).
+///
+/// Served dishes are not part of a snapshot, they have already left the kitchen.
+/// The same goes for whatever the sources of the refrigerators still hold.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// The layers of the mixing bowls, one per recipe on the call stack, listed from the bottom to the top.
+    pub mixing_bowls: Vec<BTreeMap<MixingBowlId, MixingBowl>>,
+    /// The layers of the baking dishes, one per recipe on the call stack, listed from the bottom to the top.
+    pub baking_dishes: Vec<BTreeMap<BakingDishId, BakingDish>>,
+    /// The call stack, the main recipe first.
+    pub frames: Vec<FrameSnapshot>,
+    pub refrigerators: BTreeMap<RefrigeratorId, RefrigeratorSnapshot>,
+    /// The refrigerator the current statement is waiting for, if any.
+    pub starved: Option<RefrigeratorId>,
+}
+
+/// A recipe on the call stack.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FrameSnapshot {
+    pub recipe: String,
+    pub ingredients: BTreeMap<String, Ingredient>,
+    /// The index of the next statement in the method, followed by the one in the body of each running loop, innermost last.
+    /// Every position but the last one points at the loop whose body comes next.
+    pub positions: Vec<usize>,
+    /// The running iteration of the loop of each position, starting at 1. The method itself always has 1.
+    #[serde(default)]
+    pub iterations: Vec<usize>,
+    /// The number of statements executed since the recipe was entered, counting towards its cooking time.
+    /// The clock of a wall-clock cooking time starts over on resume.
+    #[serde(default)]
+    pub steps: u64,
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Snapshot, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read snapshot {}: {}", path.display(), err))?;
+        let snapshot: Snapshot = serde_json::from_str(&json)
+            .map_err(|err| format!("failed to parse snapshot {}: {}", path.display(), err))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(format!("snapshot {} has version {}, expected {}", path.display(), snapshot.version, SNAPSHOT_VERSION));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("failed to write snapshot {}: {}", path.display(), err))?;
+        std::fs::write(path, json)
+            .map_err(|err| format!("failed to write snapshot {}: {}", path.display(), err))
+    }
+}
@@ -1,139 +1,67 @@
 use std::collections::BTreeMap;
 
-pub struct LazyTreeMap<'a, K: Ord + Clone, V: Clone> {
+/// A stack of maps where the topmost layer lazily copies values from the layers below on write.
+///
+/// Each auxiliary recipe gets its own layer, so it works on copies of its caller's values
+/// without copying the ones it never touches.
+pub struct LayeredMap<K: Ord + Clone, V: Clone> {
     value_factory: fn(&K)->V,
-    parent: LazyTreeMapParent<'a, K, V>,
-    pub delegate: BTreeMap<K, V>,
+    layers: Vec<BTreeMap<K, V>>,
 }
 
-pub enum LazyTreeMapParent<'a, K: Ord + Clone, V: Clone> {
-    None,
-    Map(&'a BTreeMap<K, V>),
-    Other(&'a LazyTreeMap<'a, K, V>)
-}
-
-impl<'a, K: Ord + Clone, V: Clone> LazyTreeMap<'a, K, V> {
-    pub fn new(parent: LazyTreeMapParent<'a, K, V>, constructor: fn(&K)->V) -> LazyTreeMap<'a, K, V> {
-        LazyTreeMap{
-            parent,
+impl<K: Ord + Clone, V: Clone> LayeredMap<K, V> {
+    pub fn new(base: BTreeMap<K, V>, constructor: fn(&K)->V) -> LayeredMap<K, V> {
+        LayeredMap {
             value_factory: constructor,
-            delegate: BTreeMap::new(),
+            layers: vec![base],
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<&V> {
-        self.delegate.get(key).or_else(|| {
-            match self.parent {
-                LazyTreeMapParent::None => { None }
-                LazyTreeMapParent::Map(map) => {
-                    map.get(&key)
-                },
-                LazyTreeMapParent::Other(other) => {
-                    other.get(&key)
-                }
-            }
-        })
-    }
-
-    pub fn get_mut(&mut self, key: K) -> &mut V {
-        if self.delegate.contains_key(&key) {
-            return self.delegate.get_mut(&key).unwrap();
+    /// Restores a map from its layers, listed from the bottom to the top.
+    pub fn from_layers(mut layers: Vec<BTreeMap<K, V>>, constructor: fn(&K)->V) -> LayeredMap<K, V> {
+        if layers.is_empty() {
+            layers.push(BTreeMap::new());
         }
-
-        let mut value: Option<V> = None;
-        match self.parent {
-            LazyTreeMapParent::Map(map) => {
-                value = map.get(&key).map(V::clone);
-            },
-            LazyTreeMapParent::Other(other) => {
-                value = other.get(&key).clone().map(V::clone);
-            },
-            LazyTreeMapParent::None => {}
+        LayeredMap {
+            value_factory: constructor,
+            layers,
         }
-        let value = value.unwrap_or_else(|| (self.value_factory)(&key));
-        self.delegate.insert(key.clone(), value);
-        return self.delegate.get_mut(&key).unwrap();
     }
-}
 
-pub fn check_for_number(buffer: &mut String) -> bool {
-    if buffer.trim().is_empty() {
-        std::io::stdin().read_line(buffer).expect("failed to read from STDIN");
+    /// All layers, listed from the bottom to the top.
+    pub fn layers(&self) -> &[BTreeMap<K, V>] {
+        &self.layers
     }
 
-    if let Some(sym) =  buffer.chars().peekable().peek() {
-        return sym.is_numeric();
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.layers.iter().rev().find_map(|layer| layer.get(key))
     }
-    return false;
-}
-
-pub fn read_number(buffer: &mut String) -> f64 {
-    loop {
-        if buffer.is_empty() {
-            std::io::stdin().read_line(buffer).expect("failed to read from STDIN");
-            continue;
-        }
-        let mut chars = buffer.chars().peekable();
-        let mut val = 0f64;
-        let mut neg = false;
-        let mut int_part = false;
-        let mut dec_part = false;
-        let mut dec_precision = 0.1f64;
 
-        loop {
-            let sym = chars.peek();
-            if let Some(sym) = sym {
-                if dec_part {
-                    if sym.is_numeric() {
-                        val += sym.to_digit(10).unwrap() as f64 * dec_precision;
-                        dec_precision /= 10.0;
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                } else if int_part {
-                    if *sym == '.' {
-                        dec_part = true;
-                        chars.next();
-                    } else if sym.is_numeric() {
-                        val = val * 10.0 + sym.to_digit(10).unwrap() as f64;
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                } else {
-                    if *sym == '-' {
-                        neg = true;
-                    } else if sym.is_numeric() {
-                        val += sym.to_digit(10).unwrap() as f64;
-                        int_part = true;
-                    } else {
-                        neg = false;
-                    }
-                    chars.next();
-                }
-            } else {
-                break;
-            }
+    pub fn get_mut(&mut self, key: K) -> &mut V {
+        let top = self.layers.len() - 1;
+        if !self.layers[top].contains_key(&key) {
+            let value = self.layers[..top].iter().rev()
+                .find_map(|layer| layer.get(&key))
+                .cloned()
+                .unwrap_or_else(|| (self.value_factory)(&key));
+            self.layers[top].insert(key.clone(), value);
         }
+        self.layers[top].get_mut(&key).unwrap()
+    }
 
-        *buffer = chars.collect();
+    /// Every key with the value that [`LayeredMap::get`] would return for it.
+    pub fn visible(&self) -> BTreeMap<&K, &V> {
+        self.layers.iter().flat_map(|layer| layer.iter()).collect()
+    }
 
-        if int_part {
-            if neg {
-                val *= -1.0;
-            }
-            return val;
-        }
+    pub fn push_layer(&mut self) {
+        self.layers.push(BTreeMap::new());
     }
-}
 
-pub fn read_char(buffer: &mut String) -> u32 {
-    while buffer.is_empty() {
-        std::io::stdin().read_line(buffer).expect("failed to read from STDIN");
+    /// Drops the topmost layer. The base layer is never dropped.
+    pub fn pop_layer(&mut self) {
+        if self.layers.len() > 1 {
+            self.layers.pop();
+        }
     }
-    let mut chars = buffer.chars();
-    let res = chars.next().unwrap() as u32;
-    *buffer = chars.collect();
-    return res;
 }
@@ -0,0 +1,10 @@
+extern crate pest_derive;
+
+pub mod ast;
+pub mod cookbook;
+pub mod coverage;
+pub mod grammar;
+pub mod interpreter;
+pub mod loader;
+pub mod manifest;
+pub mod testing;
@@ -0,0 +1,202 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::ast::recipe::{borrowed_files, recipes_from, Recipes};
+use crate::ast::util::{ParseError, Span};
+use crate::grammar::{parse, Rule};
+
+/// Where a recipe has been defined.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecipeLocation {
+    pub path: PathBuf,
+    pub span: Span,
+}
+
+impl Display for RecipeLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.span)
+    }
+}
+
+/// Something that went wrong while loading recipe files.
+#[derive(Debug)]
+pub enum LoadError {
+    /// A file couldn't be read.
+    Io { path: PathBuf, error: std::io::Error },
+    /// A file isn't valid Chef.
+    Parse { path: PathBuf, error: Box<pest::error::Error<Rule>> },
+    /// A file is valid Chef, but its recipes don't make sense.
+    Transform { path: PathBuf, error: ParseError },
+    /// A borrowed file is neither next to the file borrowing from it nor on the search path.
+    NotFound { path: String, borrower: PathBuf },
+    /// Files borrow recipes from each other in a cycle, listed in borrowing order with the first one repeated at the end.
+    Cycle { paths: Vec<PathBuf> },
+    /// Two recipes have the same name.
+    Conflict { recipe: String, first: Box<RecipeLocation>, second: Box<RecipeLocation> },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "failed to read {}: {}", path.display(), error),
+            LoadError::Parse { path, error } => write!(f, "failed to parse {}:\n{}", path.display(), error),
+            LoadError::Transform { path, error } => write!(f, "{}:{}", path.display(), error),
+            LoadError::NotFound { path, borrower } => write!(f, "{} borrows recipes from {}, which cannot be found", borrower.display(), path),
+            LoadError::Cycle { paths } => {
+                let paths: Vec<_> = paths.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "recipe files borrow from each other in a cycle: {}", paths.join(" -> "))
+            }
+            LoadError::Conflict { recipe, first, second } => write!(f, "recipe {} is defined twice, at {} and at {}", recipe, first, second),
+        }
+    }
+}
+
+/// Loads a recipe file together with all files it borrows recipes from with `Borrow recipes from "<file>".`,
+/// putting all of their recipes into one namespace.
+///
+/// Borrowed files are looked up next to the borrowing file first and on the search path after that.
+/// Every file is loaded once, no matter how many files borrow from it.
+pub struct Loader {
+    search_path: Vec<PathBuf>,
+    recipes: Recipes,
+    locations: HashMap<String, RecipeLocation>,
+    loaded: HashSet<PathBuf>,
+    /// The files that are being loaded, each borrowing from the next one, by their canonical and their given path.
+    borrowing: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Loader {
+    pub fn new(search_path: Vec<PathBuf>) -> Loader {
+        Loader {
+            search_path,
+            recipes: Recipes::new(),
+            locations: HashMap::new(),
+            loaded: HashSet::new(),
+            borrowing: Vec::new(),
+        }
+    }
+
+    /// Loads a file and everything it borrows from.
+    /// The file's own recipes come first, so its first recipe stays the main recipe.
+    pub fn load(self, path: &Path) -> Result<Recipes, LoadError> {
+        self.load_located(path).map(|(recipes, _)| recipes)
+    }
+
+    /// Loads a file and everything it borrows from, together with where each recipe has been defined.
+    pub fn load_located(mut self, path: &Path) -> Result<(Recipes, HashMap<String, RecipeLocation>), LoadError> {
+        self.load_file(path)?;
+        Ok((self.recipes, self.locations))
+    }
+
+    /// Loads recipes that don't come from a file, e.g. from stdin, and everything they borrow from.
+    /// `path` is what errors call them, and borrowed files are looked up relative to it.
+    pub fn load_source(self, path: &Path, code: &str) -> Result<Recipes, LoadError> {
+        self.load_source_located(path, code).map(|(recipes, _)| recipes)
+    }
+
+    /// Like [`load_source`](Loader::load_source), together with where each recipe has been defined.
+    pub fn load_source_located(mut self, path: &Path, code: &str) -> Result<(Recipes, HashMap<String, RecipeLocation>), LoadError> {
+        self.load_code(path, path.to_path_buf(), code)?;
+        Ok((self.recipes, self.locations))
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), LoadError> {
+        let canonical = path.canonicalize()
+            .map_err(|error| LoadError::Io { path: path.to_path_buf(), error })?;
+        let code = std::fs::read_to_string(path)
+            .map_err(|error| LoadError::Io { path: path.to_path_buf(), error })?;
+        self.load_code(path, canonical, &code)
+    }
+
+    fn load_code(&mut self, path: &Path, canonical: PathBuf, code: &str) -> Result<(), LoadError> {
+        if let Some(start) = self.borrowing.iter().position(|(borrowing, _)| *borrowing == canonical) {
+            let mut paths: Vec<_> = self.borrowing[start..].iter().map(|(_, path)| path.clone()).collect();
+            paths.push(path.to_path_buf());
+            return Err(LoadError::Cycle { paths });
+        }
+        if !self.loaded.insert(canonical.clone()) {
+            return Ok(());
+        }
+
+        let recipes_rule = parse(code)
+            .map_err(|error| LoadError::Parse { path: path.to_path_buf(), error })?
+            .next().unwrap();
+        let borrowed = borrowed_files(&recipes_rule);
+        let recipes = recipes_from(recipes_rule)
+            .map_err(|error| LoadError::Transform { path: path.to_path_buf(), error })?;
+        for (name, recipe) in recipes {
+            let location = RecipeLocation { path: path.to_path_buf(), span: recipe.span() };
+            if let Some(first) = self.locations.get(&name) {
+                return Err(LoadError::Conflict { recipe: name, first: Box::new(first.clone()), second: Box::new(location) });
+            }
+            self.locations.insert(name.clone(), location);
+            self.recipes.insert(name, recipe);
+        }
+
+        self.borrowing.push((canonical, path.to_path_buf()));
+        for borrowed_path in borrowed {
+            let resolved = self.resolve(&borrowed_path, path)?;
+            self.load_file(&resolved)?;
+        }
+        self.borrowing.pop();
+        Ok(())
+    }
+
+    fn resolve(&self, borrowed: &str, borrower: &Path) -> Result<PathBuf, LoadError> {
+        let next_to_borrower = borrower.parent().unwrap_or_else(|| Path::new("")).join(borrowed);
+        std::iter::once(next_to_borrower)
+            .chain(self.search_path.iter().map(|dir| dir.join(borrowed)))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| LoadError::NotFound { path: borrowed.to_string(), borrower: borrower.to_path_buf() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/loader").join(path)
+    }
+
+    #[test]
+    fn cycles_are_reported_in_borrowing_order() {
+        match Loader::new(Vec::new()).load(&fixture("cycle/a.chef")) {
+            Err(LoadError::Cycle { paths }) => {
+                assert_eq!(paths, vec![fixture("cycle/a.chef"), fixture("cycle/b.chef"), fixture("cycle/a.chef")]);
+            }
+            other => panic!("expected a cycle, got {:?}", other.map(|recipes| recipes.keys().cloned().collect::<Vec<_>>())),
+        }
+    }
+
+    #[test]
+    fn diamonds_load_shared_files_once() {
+        let (recipes, locations) = Loader::new(Vec::new()).load_located(&fixture("diamond/main.chef")).unwrap();
+        assert_eq!(recipes.keys().collect::<Vec<_>>(), vec!["main", "left", "base", "right"]);
+        assert_eq!(locations["base"].path, fixture("diamond/base.chef"));
+    }
+
+    #[test]
+    fn duplicate_recipes_report_both_locations() {
+        match Loader::new(Vec::new()).load(&fixture("conflict/main.chef")) {
+            Err(LoadError::Conflict { recipe, first, second }) => {
+                assert_eq!(recipe, "shared");
+                assert_eq!(*first, RecipeLocation { path: fixture("conflict/main.chef"), span: first.span });
+                assert_eq!(first.span.begin, (13, 1));
+                assert_eq!(*second, RecipeLocation { path: fixture("conflict/other.chef"), span: second.span });
+                assert_eq!(second.span.begin, (11, 1));
+            }
+            other => panic!("expected a conflict, got {:?}", other.map(|recipes| recipes.keys().cloned().collect::<Vec<_>>())),
+        }
+    }
+
+    #[test]
+    fn borrowed_files_are_looked_up_on_the_search_path() {
+        let code = "Borrow recipes from \"base.chef\".\n\nTop.\n\nIngredients.\n1 g thyme\n\nMethod.\nPut thyme into the mixing bowl.\n";
+        let err = Loader::new(Vec::new()).load_source(Path::new("<stdin>"), code).unwrap_err();
+        assert!(matches!(err, LoadError::NotFound { ref path, .. } if path == "base.chef"), "{}", err);
+        let recipes = Loader::new(vec![fixture("diamond")]).load_source(Path::new("<stdin>"), code).unwrap();
+        assert_eq!(recipes.keys().collect::<Vec<_>>(), vec!["top", "base"]);
+    }
+}
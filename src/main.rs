@@ -1,64 +1,98 @@
-extern crate pest_derive;
+use std::path::PathBuf;
 
-use std::io::Read;
+use clap::{Parser as ArgParser, Subcommand};
 
-use clap::Parser as ArgParser;
-use pest::Parser;
+use crate::cli::cookbook::CookbookCommand;
+use crate::cli::run::RunOpts;
+use crate::cli::EXIT_STATUS_HELP;
 
-use crate::ast::recipe::recipes_from;
-use crate::grammar::{ChefParser, Rule};
-use crate::interpreter::interpreter::Interpreter;
-
-mod ast;
-mod grammar;
-mod interpreter;
+mod cli;
 
 #[derive(ArgParser)]
 #[clap(
+    name = "chef",
     version = "0.1.0",
     author = "Siphalor <info@siphalor.de>",
     rename_all = "kebab",
     about = "Chef interpreter/compiler in Rust",
+    after_help = EXIT_STATUS_HELP,
 )]
-struct Opts {
-    /// An input file
-    #[clap(required=true)]
-    input: String,
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
 }
 
-fn main() {
-    let opts: Opts = Opts::parse();
-
-    println!("Hello, kitchen!");
-
-    match std::fs::File::open(opts.input) {
-        Ok(mut file) => {
-            let mut code = String::new();
-            if let Err(err) = file.read_to_string(&mut code) {
-                eprintln!("file read error: {}", err);
-            }
+#[derive(Subcommand)]
+enum Command {
+    /// Cook a recipe file, or the project described by the nearest Cookbook.toml
+    #[clap(after_help = EXIT_STATUS_HELP)]
+    Run(Box<RunOpts>),
+    /// Check that recipe files can be read and that every recipe they serve with exists,
+    /// by default all recipe files of the project
+    Check {
+        /// The recipe file, or - to read it from stdin
+        recipe: Option<String>,
+        /// The manifest of the project
+        #[clap(long, value_name = "FILE", conflicts_with = "recipe")]
+        manifest: Option<PathBuf>,
+        /// Also look for files borrowed with `Borrow recipes from` in this directory
+        #[clap(long, short = 'I', value_name = "DIR")]
+        include: Vec<PathBuf>,
+    },
+    /// Lay out recipe files the canonical way and print them
+    Fmt {
+        /// The recipe files, or - to read one from stdin
+        #[clap(required = true)]
+        recipes: Vec<String>,
+        /// Rewrite the files instead of printing them
+        #[clap(long)]
+        write: bool,
+        /// Only report files that aren't laid out the canonical way
+        #[clap(long, conflicts_with = "write")]
+        check: bool,
+    },
+    /// Print the syntax tree of the recipes in a file
+    Ast {
+        /// The recipe file, or - to read it from stdin
+        recipe: String,
+        /// Also look for files borrowed with `Borrow recipes from` in this directory
+        #[clap(long, short = 'I', value_name = "DIR")]
+        include: Vec<PathBuf>,
+    },
+    /// Run test cases from recipe comments, sidecar files and the project manifest
+    Test {
+        /// Recipe files, and directories to search for them, whose tests to run
+        /// [default: the test cases and recipe files of the nearest Cookbook.toml]
+        paths: Vec<PathBuf>,
+        /// The manifest of the project
+        #[clap(long, value_name = "FILE", conflicts_with = "paths")]
+        manifest: Option<PathBuf>,
+        /// Also look for files borrowed with `Borrow recipes from` in this directory
+        #[clap(long, short = 'I', value_name = "DIR")]
+        include: Vec<PathBuf>,
+        /// How many test cases to run at the same time [default: the number of CPUs]
+        #[clap(long, short = 'j', value_name = "N")]
+        jobs: Option<usize>,
+        /// Write which statements the test cases ran to this file as an lcov tracefile and print a summary
+        #[clap(long, value_name = "FILE")]
+        coverage: Option<PathBuf>,
+    },
+    /// Browse the auxiliary recipes that ship with the interpreter
+    Cookbook {
+        #[clap(subcommand)]
+        command: CookbookCommand,
+    },
+}
 
-            let parsed = ChefParser::parse(Rule::recipes, code.as_str());
-            match parsed {
-                Ok(mut parsed) => {
-                    match recipes_from(parsed.next().unwrap()) {
-                        Ok(recipes) => {
-                            if let Err(err) = Interpreter::new(recipes).run_main() {
-                                println!("error: {:?}", err);
-                            }
-                        }
-                        Err(err) => {
-                            println!("transform error:\n{:?}", err);
-                        }
-                    }
-                },
-                Err(err) => {
-                    println!("parse error:\n{:?}", err);
-                }
-            }
-        }
-        Err(err) => {
-            eprintln!("failed to open file: {}", err);
-        }
-    }
+fn main() {
+    let cli: Cli = Cli::parse();
+    let exit_code = match cli.command {
+        Command::Run(opts) => cli::run::run(*opts),
+        Command::Check { recipe, manifest, include } => cli::check::check(recipe, manifest, include),
+        Command::Fmt { recipes, write, check } => cli::fmt::format_files(&recipes, write, check),
+        Command::Ast { recipe, include } => cli::ast::print_ast(&recipe, &include),
+        Command::Test { paths, manifest, include, jobs, coverage } => cli::test::test(paths, manifest, include, jobs, coverage),
+        Command::Cookbook { command } => cli::cookbook::browse_cookbook(&command),
+    };
+    std::process::exit(exit_code);
 }
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::ast::util::RefrigeratorId;
+
+use crate::interpreter::interpreter::{CookingTime, InterpreterOptions, RuntimeChecks};
+use crate::interpreter::number::JunkPolicy;
+use crate::interpreter::output::OutputFormat;
+use crate::interpreter::refrigerator::{EofBehavior, RefrigeratorSource};
+
+/// The name of the file that describes a Chef project.
+pub const MANIFEST_NAME: &str = "Cookbook.toml";
+
+/// A Chef project, described by a `Cookbook.toml` next to its recipes.
+/// All paths are relative to the directory of the manifest.
+///
+/// ```toml
+/// main = "src/main.chef"
+/// entry = "Caramel Sauce"
+/// sources = ["src", "lib"]
+///
+/// [input]
+/// 1 = "input.txt"
+/// 2 = "text:42"
+///
+/// [settings]
+/// checks = "strict"
+/// eof = "sentinel"
+///
+/// [[test]]
+/// name = "adds two numbers"
+/// input = "3 4\n"
+/// output = "7"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    /// The file with the main recipe.
+    pub main: PathBuf,
+    /// The recipe to run instead of the first one in the main file.
+    pub entry: Option<String>,
+    /// Directories with more recipe files. Borrowed files are looked up here, and `check` checks all files in them.
+    pub sources: Vec<PathBuf>,
+    /// Where refrigerators are stocked from instead of stdin, by their number, written like `--fridge`.
+    /// A single source stocks the first refrigerator.
+    #[serde(deserialize_with = "refrigerator_sources")]
+    pub input: BTreeMap<RefrigeratorId, RefrigeratorSource>,
+    pub settings: ManifestSettings,
+    #[serde(rename = "test")]
+    pub tests: Vec<TestCase>,
+    /// The directory of the manifest.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// The interpreter settings of a project, named like the command line options.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ManifestSettings {
+    #[serde(deserialize_with = "parsed")]
+    pub eof: Option<EofBehavior>,
+    #[serde(deserialize_with = "parsed")]
+    pub junk: Option<JunkPolicy>,
+    #[serde(deserialize_with = "parsed")]
+    pub checks: Option<RuntimeChecks>,
+    #[serde(deserialize_with = "parsed")]
+    pub format: Option<OutputFormat>,
+    #[serde(deserialize_with = "parsed")]
+    pub cooking_time: Option<CookingTime>,
+    pub bytes: Option<bool>,
+}
+
+/// Reads a setting from its command line form.
+fn parsed<'de, D: Deserializer<'de>, T: FromStr<Err=String>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Reads the refrigerator sources of a project, either a single one for the first refrigerator
+/// or a table of them by refrigerator number.
+fn refrigerator_sources<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<RefrigeratorId, RefrigeratorSource>, D::Error> {
+    struct SourcesVisitor;
+
+    impl<'de> Visitor<'de> for SourcesVisitor {
+        type Value = BTreeMap<RefrigeratorId, RefrigeratorSource>;
+
+        fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+            write!(f, "a refrigerator source or a table of them by refrigerator number")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, source: &str) -> Result<Self::Value, E> {
+            Ok(BTreeMap::from([(1, source.parse().map_err(E::custom)?)]))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut sources = BTreeMap::new();
+            while let Some((refrigerator, source)) = map.next_entry::<String, String>()? {
+                let refrigerator = refrigerator.trim().parse()
+                    .map_err(|err| serde::de::Error::custom(format!("invalid refrigerator number {}: {}", refrigerator, err)))?;
+                sources.insert(refrigerator, source.parse().map_err(serde::de::Error::custom)?);
+            }
+            Ok(sources)
+        }
+    }
+
+    deserializer.deserialize_any(SourcesVisitor)
+}
+
+/// A run of the project together with what it is expected to serve.
+/// Test cases can also be written into recipe comments, see [`testing`](crate::testing).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TestCase {
+    pub name: String,
+    /// The recipe to run instead of the project's entry recipe.
+    pub entry: Option<String>,
+    /// What the first refrigerator is stocked with.
+    pub input: Option<String>,
+    /// A file that the first refrigerator is stocked from, instead of `input`.
+    pub input_file: Option<PathBuf>,
+    /// What is expected to be served.
+    pub output: Option<String>,
+    /// A file with what is expected to be served, instead of `output`.
+    pub output_file: Option<PathBuf>,
+    /// The status the recipe is expected to be thrown away with, 0 if it is expected to finish normally.
+    pub exit_code: Option<i32>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let toml = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read manifest {}: {}", path.display(), err))?;
+        let mut manifest: Manifest = toml::from_str(&toml)
+            .map_err(|err| format!("failed to parse manifest {}: {}", path.display(), err))?;
+        if manifest.main.as_os_str().is_empty() {
+            return Err(format!("manifest {} does not name the main recipe file", path.display()));
+        }
+        manifest.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    /// Looks for a manifest in the given directory and all of its parents.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(MANIFEST_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Resolves a path given in the manifest.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    pub fn main_path(&self) -> PathBuf {
+        self.resolve(&self.main)
+    }
+
+    pub fn source_dirs(&self) -> Vec<PathBuf> {
+        self.sources.iter().map(|dir| self.resolve(dir)).collect()
+    }
+
+    pub fn interpreter_options(&self) -> InterpreterOptions {
+        let settings = &self.settings;
+        let bytes = settings.bytes.unwrap_or_default();
+        let default_eof = if bytes { EofBehavior::Sentinel } else { EofBehavior::Error };
+        InterpreterOptions {
+            eof_behavior: settings.eof.unwrap_or(default_eof),
+            junk_policy: settings.junk.unwrap_or_default(),
+            output_format: settings.format.unwrap_or_default(),
+            bytes,
+            checks: settings.checks.unwrap_or_default(),
+            cooking_time: settings.cooking_time.unwrap_or_default(),
+            ..InterpreterOptions::default()
+        }
+    }
+}
+
+impl TestCase {
+    /// Reads the input, with `input_file` relative to `root`.
+    pub fn input(&self, root: &Path) -> Result<Vec<u8>, String> {
+        match &self.input_file {
+            Some(path) => std::fs::read(root.join(path))
+                .map_err(|err| format!("failed to read the input of test {}: {}", self.name, err)),
+            None => Ok(self.input.clone().unwrap_or_default().into_bytes()),
+        }
+    }
+
+    /// Reads the expected output, with `output_file` relative to `root`.
+    pub fn expected_output(&self, root: &Path) -> Result<Vec<u8>, String> {
+        match &self.output_file {
+            Some(path) => std::fs::read(root.join(path))
+                .map_err(|err| format!("failed to read the expected output of test {}: {}", self.name, err)),
+            None => Ok(self.output.clone().unwrap_or_default().into_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_stocks_refrigerators_by_number() {
+        let manifest: Manifest = toml::from_str("main = \"main.chef\"\n[input]\n1 = \"input.txt\"\n2 = \"text:42\"\n3 = \"stdin\"\n").unwrap();
+        assert_eq!(manifest.input, BTreeMap::from([
+            (1, RefrigeratorSource::File(PathBuf::from("input.txt"))),
+            (2, RefrigeratorSource::Text("42".to_string())),
+            (3, RefrigeratorSource::Stdin),
+        ]));
+
+        let manifest: Manifest = toml::from_str("main = \"main.chef\"\ninput = \"input.txt\"\n").unwrap();
+        assert_eq!(manifest.input, BTreeMap::from([(1, RefrigeratorSource::File(PathBuf::from("input.txt")))]));
+
+        let err = toml::from_str::<Manifest>("main = \"main.chef\"\n[input]\nfirst = \"input.txt\"\n").unwrap_err();
+        assert!(err.to_string().contains("invalid refrigerator number first"), "{}", err);
+    }
+}
@@ -0,0 +1,367 @@
+//! Running recipes against what they are expected to serve.
+//!
+//! Besides the `[[test]]` tables of a `Cookbook.toml`, test cases can be written into the comment of any recipe:
+//!
+//! ```text
+//! Test: adds two numbers
+//! In: 3 4
+//! Out: 7
+//! ```
+//!
+//! A case starts with a `Test:` line naming it, followed by lines that describe it:
+//! every `In:` line is a line of input, the `Out:` lines are joined with line breaks into what is expected to be served,
+//! `Entry:` runs another recipe than the one whose comment it is and `Exit code:` is the status the recipe is expected
+//! to be thrown away with. The case ends at the first line that is none of these.
+//!
+//! A recipe file `add.chef` can also be tested by sidecar files: `add.out` holds what it is expected to serve
+//! and `add.in`, if there is one, what the first refrigerator is stocked with.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::ast::recipe::recipes_from;
+use crate::coverage::{CoverageObserver, CoverageReport};
+use crate::grammar::parse;
+use crate::interpreter::interpreter::{Interpreter, InterpreterOptions};
+use crate::interpreter::output::{MemorySink, Output};
+use crate::interpreter::refrigerator::Refrigerators;
+use crate::loader::Loader;
+use crate::manifest::TestCase;
+
+/// A test case together with everything needed to run it.
+#[derive(Clone, Debug)]
+pub struct TestJob {
+    /// Where the test case has been found, to tell it apart from others of the same name.
+    pub source: PathBuf,
+    /// The file with the recipes to run.
+    pub recipe_file: PathBuf,
+    /// Where files borrowed by the recipe file are looked up.
+    pub search_path: Vec<PathBuf>,
+    pub options: InterpreterOptions,
+    /// The directory that the input and output files of the case are relative to.
+    pub root: PathBuf,
+    pub case: TestCase,
+    /// Whether to record which statements have been run.
+    pub coverage: bool,
+}
+
+/// What came out of running a test case.
+#[derive(Clone, Debug)]
+pub struct TestOutcome {
+    /// Whether the test case passed, or a description of what went wrong.
+    pub result: Result<(), String>,
+    /// Which statements have been run, if the job asked for it and the recipes could be loaded.
+    pub coverage: Option<CoverageReport>,
+}
+
+/// Reads the test cases from the comment of a recipe.
+pub fn comment_cases(comment: &str) -> Result<Vec<TestCase>, String> {
+    let mut cases = Vec::new();
+    let mut current: Option<(TestCase, Vec<&str>)> = None;
+    for line in comment.lines().map(str::trim_start) {
+        if let Some(name) = line.strip_prefix("Test:") {
+            cases.extend(current.take().map(finish_case));
+            current = Some((TestCase { name: name.trim().to_string(), ..TestCase::default() }, Vec::new()));
+            continue;
+        }
+        let (case, output) = match current.as_mut() {
+            Some(current) => current,
+            None => continue,
+        };
+        if let Some(input) = field(line, "In:") {
+            let buffer = case.input.get_or_insert_with(String::new);
+            buffer.push_str(input);
+            buffer.push('\n');
+        } else if let Some(line) = field(line, "Out:") {
+            output.push(line);
+        } else if let Some(entry) = field(line, "Entry:") {
+            case.entry = Some(entry.trim().to_string());
+        } else if let Some(exit_code) = field(line, "Exit code:") {
+            case.exit_code = Some(exit_code.trim().parse()
+                .map_err(|err| format!("invalid exit code {} in test {}: {}", exit_code, case.name, err))?);
+        } else {
+            cases.extend(current.take().map(finish_case));
+        }
+    }
+    cases.extend(current.map(finish_case));
+    Ok(cases)
+}
+
+/// The value of a `<key> <value>` line, without the space after the key.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.strip_prefix(key).map(|value| value.strip_prefix(' ').unwrap_or(value))
+}
+
+fn finish_case((mut case, output): (TestCase, Vec<&str>)) -> TestCase {
+    if !output.is_empty() {
+        case.output = Some(output.join("\n"));
+    }
+    case
+}
+
+/// Reads the test cases of a recipe file: those in the comments of its recipes, which run the recipe they belong to,
+/// and the one described by its sidecar files.
+/// Files that are borrowed from aren't looked at.
+pub fn file_cases(path: &Path) -> Result<Vec<TestCase>, String> {
+    let code = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    let recipes_rule = parse(&code)
+        .map_err(|err| format!("failed to parse {}:\n{}", path.display(), err))?
+        .next().unwrap();
+    let recipes = recipes_from(recipes_rule)
+        .map_err(|err| format!("{}:{}", path.display(), err))?;
+
+    let mut cases = Vec::new();
+    for (name, recipe) in &recipes {
+        if let Some(comment) = recipe.comment() {
+            let found = comment_cases(comment).map_err(|err| format!("{}: {}", path.display(), err))?;
+            cases.extend(found.into_iter().map(|case| TestCase { entry: case.entry.or_else(|| Some(name.clone())), ..case }));
+        }
+    }
+    cases.extend(sidecar_case(path));
+    Ok(cases)
+}
+
+/// The test case described by the `.in` and `.out` files next to a recipe file, with paths relative to its directory.
+pub fn sidecar_case(path: &Path) -> Option<TestCase> {
+    let output_file = path.with_extension("out");
+    if !output_file.is_file() {
+        return None;
+    }
+    let input_file = path.with_extension("in");
+    Some(TestCase {
+        name: output_file.file_name()?.to_string_lossy().into_owned(),
+        input_file: if input_file.is_file() { Some(PathBuf::from(input_file.file_name()?)) } else { None },
+        output_file: Some(PathBuf::from(output_file.file_name()?)),
+        ..TestCase::default()
+    })
+}
+
+/// Runs a test case with in-memory input and output.
+/// Fails with a description of what went wrong, including a diff if the wrong thing has been served.
+///
+/// Trailing line breaks are ignored when comparing what has been served.
+pub fn run_test(job: &TestJob) -> TestOutcome {
+    let mut coverage = None;
+    let result = cook_test(job, &mut coverage);
+    TestOutcome { result, coverage }
+}
+
+fn cook_test(job: &TestJob, coverage: &mut Option<CoverageReport>) -> Result<(), String> {
+    let case = &job.case;
+    let (recipes, locations) = Loader::new(job.search_path.clone()).load_located(&job.recipe_file)
+        .map_err(|err| err.to_string())?;
+    let input = case.input(&job.root)?;
+    let expected = case.expected_output(&job.root)?;
+
+    let mut interpreter = Interpreter::with_options(recipes, job.options.clone());
+    let observer = Rc::new(CoverageObserver::new());
+    if job.coverage {
+        interpreter.add_observer(observer.clone());
+    }
+    let mut refrigerators = Refrigerators::new(job.options.refrigerator_settings());
+    refrigerators.stock(1, Box::new(Cursor::new(input)));
+    let served = MemorySink::default();
+    let examined = MemorySink::default();
+    let mut output = Output::new(Box::new(served.clone()), job.options.output_format, job.options.bytes);
+    output.set_debug_sink(Some(Box::new(examined.clone())));
+    let entry = match &case.entry {
+        Some(entry) => entry.as_str(),
+        None => interpreter.main_recipe().map_err(|err| err.to_string())?,
+    };
+    let result = interpreter.run_entry_with(entry, &mut refrigerators, &mut output);
+    if job.coverage {
+        let mut report = CoverageReport::default();
+        report.add(interpreter.recipes(), &locations, &observer.coverage());
+        *coverage = Some(report);
+    }
+
+    let examined = String::from_utf8_lossy(&examined.contents()).into_owned();
+    let with_examined = |message: String| if examined.is_empty() {
+        message
+    } else {
+        format!("{}\nexamined:\n{}", message, examined.trim_end())
+    };
+    let exit_code = result.map_err(|err| with_examined(match err.backtrace {
+        Some(ref backtrace) => format!("error: {}\n{}", err, backtrace),
+        None => format!("error: {}", err),
+    }))?.unwrap_or(0);
+
+    let expected = String::from_utf8_lossy(&expected).into_owned();
+    let served = String::from_utf8_lossy(&served.contents()).into_owned();
+    if expected.trim_end_matches(['\r', '\n']) != served.trim_end_matches(['\r', '\n']) {
+        return Err(with_examined(diff(&expected, &served)));
+    }
+    let expected_exit_code = case.exit_code.unwrap_or(0);
+    if exit_code != expected_exit_code {
+        return Err(with_examined(format!("thrown away with status {}, expected {}", exit_code, expected_exit_code)));
+    }
+    Ok(())
+}
+
+/// Runs test cases on the given number of threads and returns their outcomes in the same order.
+pub fn run_tests(jobs: &[TestJob], threads: usize) -> Vec<TestOutcome> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; jobs.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let job = match jobs.get(index) {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    let result = run_test(job);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+}
+
+/// A line diff from what was expected to what has been served,
+/// with removed lines marked by `-`, added lines by `+` and common ones by a space.
+pub fn diff(expected: &str, served: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let served: Vec<&str> = served.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and served[j..].
+    let mut common = vec![vec![0usize; served.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..served.len()).rev() {
+            common[i][j] = if expected[i] == served[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![String::from("--- expected"), String::from("+++ served")];
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < served.len() {
+        if i < expected.len() && j < served.len() && expected[i] == served[j] {
+            lines.push(format!(" {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < served.len() && (i == expected.len() || common[i][j + 1] > common[i + 1][j]) {
+            lines.push(format!("+{}", served[j]));
+            j += 1;
+        } else {
+            lines.push(format!("-{}", expected[i]));
+            i += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+    }
+
+    #[test]
+    fn comment_cases_read_every_field() {
+        let comment = "Adds numbers.
+
+Test: adds two numbers
+In: 3 4
+In:5
+Out: 7
+Out:  9
+Entry: Adding
+Exit code: 2
+This line ends the case.
+Out: 1
+
+  Test: indented
+Out: 0";
+        let cases = comment_cases(comment).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].name, "adds two numbers");
+        assert_eq!(cases[0].input.as_deref(), Some("3 4\n5\n"));
+        assert_eq!(cases[0].output.as_deref(), Some("7\n 9"));
+        assert_eq!(cases[0].entry.as_deref(), Some("Adding"));
+        assert_eq!(cases[0].exit_code, Some(2));
+        assert_eq!(cases[1].name, "indented");
+        assert_eq!(cases[1].input, None);
+        assert_eq!(cases[1].output.as_deref(), Some("0"));
+        assert_eq!(cases[1].entry, None);
+    }
+
+    #[test]
+    fn comment_cases_reject_invalid_exit_codes() {
+        let err = comment_cases("Test: broken\nExit code: three").unwrap_err();
+        assert_eq!(err, "invalid exit code three in test broken: invalid digit found in string");
+        assert!(comment_cases("No tests here.\nOut: 1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn sidecar_files_are_found_next_to_the_recipe() {
+        let case = sidecar_case(&fixture("cookbook/src/echo.chef")).unwrap();
+        assert_eq!(case.name, "echo.out");
+        assert_eq!(case.input_file, Some(PathBuf::from("echo.in")));
+        assert_eq!(case.output_file, Some(PathBuf::from("echo.out")));
+        assert!(sidecar_case(&fixture("cookbook/src/main.chef")).is_none());
+    }
+
+    #[test]
+    fn file_cases_run_the_recipe_they_belong_to() {
+        let cases = file_cases(&fixture("cookbook/src/main.chef")).unwrap();
+        let entries: Vec<_> = cases.iter().map(|case| case.entry.as_deref()).collect();
+        assert_eq!(entries, vec![Some("doubling"), Some("Halving")]);
+        let cases = file_cases(&fixture("cookbook/src/echo.chef")).unwrap();
+        assert_eq!(cases.len(), 1);
+    }
+
+    #[test]
+    fn diff_marks_removed_and_added_lines() {
+        assert_eq!(diff("a\nb\nc", "a\nc\nd"), "--- expected\n+++ served\n a\n-b\n c\n+d");
+        assert_eq!(diff("", "x"), "--- expected\n+++ served\n+x");
+        assert_eq!(diff("same", "same"), "--- expected\n+++ served\n same");
+    }
+
+    fn job(file: &str, case: TestCase) -> TestJob {
+        let recipe_file = fixture(file);
+        TestJob {
+            source: recipe_file.clone(),
+            root: recipe_file.parent().unwrap().to_path_buf(),
+            recipe_file,
+            search_path: Vec::new(),
+            options: InterpreterOptions::default(),
+            case,
+            coverage: false,
+        }
+    }
+
+    #[test]
+    fn failing_cases_describe_what_went_wrong() {
+        let path = fixture("failing/wrong.chef");
+        let case = file_cases(&path).unwrap().remove(0);
+        let outcome = run_test(&job("failing/wrong.chef", case.clone()));
+        assert_eq!(outcome.result.unwrap_err(), "--- expected\n+++ served\n-7\n+-1");
+
+        let thrown = TestCase { exit_code: Some(1), output: Some("-1".to_string()), ..case };
+        let outcome = run_test(&job("failing/wrong.chef", thrown));
+        assert_eq!(outcome.result.unwrap_err(), "thrown away with status 0, expected 1");
+    }
+
+    #[test]
+    fn passing_cases_run_in_parallel_in_order() {
+        let mut jobs: Vec<_> = file_cases(&fixture("cookbook/src/main.chef")).unwrap().into_iter()
+            .map(|case| job("cookbook/src/main.chef", case))
+            .collect();
+        jobs.extend(file_cases(&fixture("failing/wrong.chef")).unwrap().into_iter()
+            .map(|case| job("failing/wrong.chef", case)));
+        let outcomes = run_tests(&jobs, 4);
+        let passed: Vec<_> = outcomes.iter().map(|outcome| outcome.result.is_ok()).collect();
+        assert_eq!(passed, vec![true, true, false]);
+    }
+}
@@ -0,0 +1,154 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+/// Runs the chef binary in the given directory.
+fn chef(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chef"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_runs_the_cases_of_a_cookbook() {
+    let output = chef(&fixture("cookbook"), &["test", "--jobs", "2"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.ends_with("5 passed, 0 failed\n"), "{}", stdout);
+}
+
+#[test]
+fn test_fails_when_a_case_fails() {
+    let output = chef(&fixture("failing"), &["test", "."]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("adds two numbers ... FAILED\n    --- expected\n    +++ served\n    -7\n    +-1\n"), "{}", stdout);
+    assert!(stdout.ends_with("0 passed, 1 failed\n"), "{}", stdout);
+}
+
+#[test]
+fn fmt_lays_out_files_canonically() {
+    let formatted = std::fs::read_to_string(fixture("fmt/formatted.chef")).unwrap();
+    let output = chef(&fixture("fmt"), &["fmt", "unformatted.chef"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), formatted);
+
+    // Formatting what has been formatted changes nothing.
+    let output = chef(&fixture("fmt"), &["fmt", "formatted.chef"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), formatted);
+}
+
+#[test]
+fn fmt_check_lists_files_that_are_not_formatted() {
+    let output = chef(&fixture("fmt"), &["fmt", "--check", "unformatted.chef", "formatted.chef"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "unformatted.chef\n");
+
+    let output = chef(&fixture("fmt"), &["fmt", "--check", "formatted.chef"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+}
+
+#[test]
+fn input_goes_into_the_refrigerator_of_stdin() {
+    let output = chef(&fixture(""), &["run", "second_refrigerator.chef", "--fridge", "2=stdin", "--input", "4"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "4\n");
+}
+
+#[test]
+fn entry_chooses_the_recipe_to_cook() {
+    let output = chef(&fixture(""), &["run", "two_recipes.chef", "--entry", "Second"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+}
+
+#[test]
+fn unknown_entry_recipes_fail() {
+    let output = chef(&fixture(""), &["run", "two_recipes.chef", "--entry", "third"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "error: unknown recipe: third (the recipes are: first, second)\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b"");
+}
+
+#[test]
+fn ingredients_set_twice_are_a_usage_error() {
+    let output = chef(&fixture(""), &["run", "second_refrigerator.chef", "--set", "number=9", "--set", "NUMBER=10"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "invalid --set: ingredient number is set more than once\n");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn running_without_a_manifest_is_a_usage_error() {
+    let output = chef(&std::env::temp_dir(), &["run"]);
+    assert!(String::from_utf8(output.stderr).unwrap().starts_with("could not find Cookbook.toml"));
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn manifest_input_stocks_refrigerators_by_number() {
+    let output = chef(&fixture("fridges"), &["run"]);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6\n");
+    let output = chef(&fixture("fridges"), &["run", "--fridge", "2=text:7"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "7\n");
+}
+
+#[test]
+fn no_bytes_overrides_the_manifest() {
+    assert_eq!(chef(&fixture("bytes"), &["run"]).stdout, b"\xe9");
+    assert_eq!(chef(&fixture("bytes"), &["run", "--no-bytes"]).stdout, "é\n".as_bytes());
+    assert_eq!(chef(&fixture("bytes"), &["run", "--no-bytes", "--bytes"]).stdout, b"\xe9");
+}
+
+/// Waits until the main thread of a process is blocked reading from a pipe.
+#[cfg(target_os = "linux")]
+fn wait_for_pipe_read(pid: u32) {
+    let wchan = format!("/proc/{}/wchan", pid);
+    for _ in 0..1000 {
+        if std::fs::read_to_string(&wchan).unwrap_or_default().ends_with("pipe_read") {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("process {} never waited for input", pid);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn second_interrupt_while_waiting_for_input_dumps_the_kitchen() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chef"))
+        .current_dir(fixture(""))
+        .args(["run", "second_refrigerator.chef", "--fridge", "2=stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let pid = child.id().to_string();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    wait_for_pipe_read(child.id());
+    Command::new("kill").args(["-INT", &pid]).status().unwrap();
+    let mut notice = String::new();
+    stderr.read_line(&mut notice).unwrap();
+    assert_eq!(notice, "waiting for input, press Ctrl-C again to stop\n");
+    Command::new("kill").args(["-INT", &pid]).status().unwrap();
+    let mut dump = String::new();
+    stderr.read_to_string(&mut dump).unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+    assert_eq!(
+        dump,
+        "interrupted while waiting for input\n  in second refrigerator at 7:1\nKitchen of second refrigerator at call depth 1:\nThere is 0 of number\n",
+    );
+}
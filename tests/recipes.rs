@@ -0,0 +1,306 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use chef_rs::ast::recipe::recipes_from;
+use chef_rs::grammar::parse;
+use chef_rs::interpreter::interpreter::{CookingTime, Interpreter, InterpreterOptions, InterpreterResult, RuntimeChecks};
+use chef_rs::interpreter::number::JunkPolicy;
+use chef_rs::interpreter::output::{MemorySink, Output};
+use chef_rs::interpreter::refrigerator::{EofBehavior, Refrigerators};
+
+/// Parses and cooks the given recipes with the given input and returns what has been served.
+fn cook(code: &str, input: &str) -> InterpreterResult<String> {
+    cook_with(code, input, InterpreterOptions::default())
+}
+
+fn cook_with(code: &str, input: &str, options: InterpreterOptions) -> InterpreterResult<String> {
+    cook_entry(code, None, input, options)
+}
+
+/// Like `cook_with`, but cooks the recipe with the given name instead of the first one if there is one.
+fn cook_entry(code: &str, entry: Option<&str>, input: &str, options: InterpreterOptions) -> InterpreterResult<String> {
+    let recipes_rule = parse(code).unwrap_or_else(|err| panic!("recipes do not parse:\n{}", err)).next().unwrap();
+    let recipes = recipes_from(recipes_rule).unwrap_or_else(|err| panic!("recipes are broken: {:?}", err));
+    let interpreter = Interpreter::with_options(recipes, options);
+    let mut refrigerators = Refrigerators::new(interpreter.options().refrigerator_settings());
+    refrigerators.stock(1, Box::new(Cursor::new(input.as_bytes().to_vec())));
+    let served = MemorySink::default();
+    let mut output = Output::new(Box::new(served.clone()), interpreter.options().output_format, interpreter.options().bytes);
+    output.set_debug_sink(None);
+    match entry {
+        Some(entry) => interpreter.run_entry_with(entry, &mut refrigerators, &mut output)?,
+        None => interpreter.run_main_with(&mut refrigerators, &mut output)?,
+    };
+    Ok(String::from_utf8(served.contents()).unwrap())
+}
+
+#[test]
+fn taste_as_loop_verb() {
+    let code = "Tasting loop.
+
+Ingredients.
+3 g sauce
+
+Method.
+Taste the sauce.
+Put the sauce into the mixing bowl.
+Taste the sauce until tasted.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+    assert_eq!(cook(code, "").unwrap(), "1 2 3\n");
+}
+
+#[test]
+fn taste_inside_loop() {
+    let code = "Tasting inside a loop.
+
+Ingredients.
+3 g sauce
+
+Method.
+Sift the sauce.
+Put the sauce into the mixing bowl.
+Taste the sauce.
+Taste the mixing bowl for the sauce.
+Sift the sauce until sifted.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+    assert_eq!(cook(code, "").unwrap(), "1 2 3\n");
+}
+
+#[test]
+fn throw_as_loop_verb() {
+    let code = "Throwing loop.
+
+Ingredients.
+3 g away scraps
+
+Method.
+Throw away scraps.
+Put the away scraps into the mixing bowl.
+Throw away scraps until thrown.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+    assert_eq!(cook(code, "").unwrap(), "1 2 3\n");
+}
+
+#[test]
+fn examine_as_loop_verb() {
+    let code = "Examining loop.
+
+Ingredients.
+3 g sauce
+
+Method.
+Examine the sauce.
+Put the sauce into the mixing bowl.
+Examine the sauce until examined.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+    assert_eq!(cook(code, "").unwrap(), "1 2 3\n");
+}
+
+// What these samples serve has been recorded with the recursive interpreter that came before stepping,
+// with dishes rewritten to the spec format.
+
+#[test]
+fn sample_hello_world() {
+    assert_eq!(cook(include_str!("samples/hello_world.chef"), "").unwrap(), "Hello world!\n");
+}
+
+#[test]
+fn sample_fibonacci() {
+    assert_eq!(cook(include_str!("samples/fibonacci.chef"), "").unwrap(), "55 34 21 13 8 5 3 2 1 1\n");
+}
+
+#[test]
+fn sample_nested_loops() {
+    assert_eq!(cook(include_str!("samples/nested_loops.chef"), "").unwrap(), "1 2 3 1 2 3 1 2 3\n");
+}
+
+#[test]
+fn sample_stirring() {
+    assert_eq!(cook(include_str!("samples/stirring.chef"), "").unwrap(), "7 2 3 4 1 2 3 4 1\n");
+}
+
+#[test]
+fn junk_is_located_at_take() {
+    let code = "Taking.
+
+Ingredients.
+0 g number
+
+Method.
+Put number into the mixing bowl.
+Take number from refrigerator.
+
+Serves 1.
+";
+    let options = InterpreterOptions { junk_policy: JunkPolicy::Strict, ..InterpreterOptions::default() };
+    let err = cook_with(code, "  x12\n", options.clone()).unwrap_err();
+    assert_eq!(err.to_string(), "8:1 in taking: expected a number in the refrigerator, found 'x'");
+    assert_eq!(cook_with(code, "  12\n", options).unwrap(), "");
+}
+
+#[test]
+fn bytes_out_of_range_are_located_at_serves() {
+    let code = "Sentinel.
+
+Ingredients.
+0 g letter
+
+Method.
+Take letter from refrigerator.
+Put letter into the mixing bowl.
+Liquefy contents of the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+    let options = InterpreterOptions { bytes: true, eof_behavior: EofBehavior::Sentinel, ..InterpreterOptions::default() };
+    assert_eq!(cook_with(code, "65", options.clone()).unwrap(), "A");
+    let err = cook_with(code, "", options).unwrap_err();
+    assert_eq!(err.to_string(), "12:1 in sentinel: cannot serve -1 as a byte, only liquids from 0 to 255 can be served in byte mode");
+}
+
+#[test]
+fn dry_dishes_end_their_line_in_byte_mode() {
+    let code = "Dry and liquid.
+
+Ingredients.
+72 g letter
+3 g number
+
+Method.
+Put number into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+Clean the mixing bowl.
+Put letter into 2nd mixing bowl.
+Liquefy contents of the 2nd mixing bowl.
+Pour contents of the 2nd mixing bowl into the 2nd baking dish.
+
+Serves 2.
+";
+    let options = InterpreterOptions { bytes: true, ..InterpreterOptions::default() };
+    assert_eq!(cook_with(code, "", options).unwrap(), "3\nH");
+}
+
+const BURNER: &str = "Burner.
+
+Ingredients.
+3 g count
+
+Cooking time: 1 minute.
+
+Preheat oven to 180 degrees Celsius gas mark 4.
+
+Method.
+Put count into the mixing bowl.
+Stir the mixing bowl for 2 minutes.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+#[test]
+fn oven_temperature_is_prose() {
+    assert_eq!(cook(BURNER, "").unwrap(), "3\n");
+}
+
+#[test]
+fn recipes_burn_after_their_cooking_time_in_steps() {
+    let options = |per_minute| InterpreterOptions { cooking_time: CookingTime::Steps(per_minute), ..InterpreterOptions::default() };
+    assert_eq!(cook_with(BURNER, "", options(4)).unwrap(), "3\n");
+    let err = cook_with(BURNER, "", options(2)).unwrap_err();
+    assert_eq!(err.to_string(), "13:1 in burner: burner is burnt: it has been cooking for longer than 1 minutes (2 statements)");
+}
+
+#[test]
+fn recipes_burn_after_their_cooking_time_on_the_clock() {
+    let options = |per_minute| InterpreterOptions { cooking_time: CookingTime::Clock(per_minute), ..InterpreterOptions::default() };
+    assert_eq!(cook_with(BURNER, "", options(Duration::from_secs(60))).unwrap(), "3\n");
+    let err = cook_with(BURNER, "", options(Duration::from_nanos(1))).unwrap_err();
+    assert!(err.to_string().contains(" in burner: burner is burnt: it has been cooking for longer than 1 minutes (1ns)"), "{}", err);
+}
+
+#[test]
+fn backtraces_list_callers_loops_and_bowls() {
+    let code = "Caller.
+
+Ingredients.
+2 g rounds
+1 g one
+
+Method.
+Chop the rounds.
+Put rounds into the mixing bowl.
+Remove one from the mixing bowl.
+Serve with divider.
+Chop the rounds until chopped.
+
+Serves 1.
+
+Divider.
+
+Ingredients.
+0 g divisor
+1 g one
+
+Method.
+Fold divisor into the mixing bowl.
+Put one into the mixing bowl.
+Divide divisor into the mixing bowl.
+";
+    let options = InterpreterOptions { checks: RuntimeChecks::Strict, ..InterpreterOptions::default() };
+    let err = cook_with(code, "", options).unwrap_err();
+    assert_eq!(err.to_string(), "25:1 in divider: cannot divide 1 by zero");
+    assert_eq!(err.backtrace.unwrap().to_string(), "kitchen backtrace:
+  in divider at 25:1
+  served from caller at 11:1
+    in iteration 2 of the chop loop at 8:1
+mixing bowls, from the bottom to the top:
+  1: 1, 1, 1");
+}
+
+const TWO_RECIPES: &str = "First.
+
+Ingredients.
+1 g one
+
+Method.
+Put one into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Second.
+
+Ingredients.
+2 g two
+
+Method.
+Put two into the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+";
+
+#[test]
+fn entry_recipes_are_cooked_instead_of_the_first_one() {
+    assert_eq!(cook(TWO_RECIPES, "").unwrap(), "1\n");
+    assert_eq!(cook_entry(TWO_RECIPES, Some(" SECOND "), "", InterpreterOptions::default()).unwrap(), "2\n");
+}
+
+#[test]
+fn unknown_entry_recipes_are_an_error() {
+    let err = cook_entry(TWO_RECIPES, Some("third"), "", InterpreterOptions::default()).unwrap_err();
+    assert_eq!(err.to_string(), "unknown recipe: third (the recipes are: first, second)");
+}